@@ -0,0 +1,60 @@
+use crate::state::AppState;
+use axum::{
+    extract::{Request, State},
+    http::{
+        header::{AUTHORIZATION, WWW_AUTHENTICATE},
+        StatusCode,
+    },
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use sha2::{Digest, Sha256};
+
+/// Compare hashes so the time taken doesn't depend on where the inputs differ
+fn secure_eq(a: &str, b: &str) -> bool {
+    Sha256::digest(a.as_bytes()) == Sha256::digest(b.as_bytes())
+}
+
+fn is_authorized(header: Option<&str>, username: &str, password: &str) -> bool {
+    let Some(encoded) = header.and_then(|h| h.strip_prefix("Basic ")) else {
+        return false;
+    };
+    let Ok(decoded) = STANDARD.decode(encoded.trim()) else {
+        return false;
+    };
+    let Ok(decoded) = String::from_utf8(decoded) else {
+        return false;
+    };
+    match decoded.split_once(':') {
+        Some((user, pass)) => secure_eq(user, username) & secure_eq(pass, password),
+        None => false,
+    }
+}
+
+/// HTTP basic auth for the whole app, a no-op unless `[auth]` is configured
+pub async fn require_basic_auth(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some((username, password)) = state.config.auth.credentials() else {
+        return next.run(request).await;
+    };
+
+    let header = request
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|h| h.to_str().ok());
+
+    if is_authorized(header, username, password) {
+        next.run(request).await
+    } else {
+        (
+            StatusCode::UNAUTHORIZED,
+            [(WWW_AUTHENTICATE, "Basic realm=\"Draftsmith\", charset=\"UTF-8\"")],
+            "Unauthorized",
+        )
+            .into_response()
+    }
+}
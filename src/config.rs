@@ -0,0 +1,579 @@
+//! Settings for `serve`, layered from lowest to highest priority:
+//!
+//! 1. Built-in defaults
+//! 2. A TOML file (`--config`, `DRAFTSMITH_CONFIG`, or `./draftsmith.toml` if present)
+//! 3. Environment variables (`API_HOST`, `DRAFTSMITH_PORT`, ...)
+//! 4. Command line flags
+
+use chrono_tz::Tz;
+use clap::{Args, ValueEnum};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use tracing_subscriber::EnvFilter;
+
+const DEFAULT_CONFIG_FILE: &str = "draftsmith.toml";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub api: ApiConfig,
+    pub server: ServerConfig,
+    pub session: SessionConfig,
+    pub limits: LimitsConfig,
+    pub auth: AuthConfig,
+    pub cache: CacheConfig,
+    pub tls: TlsConfig,
+    pub log: LogConfig,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ApiConfig {
+    /// Full base URL, e.g. "https://host/draftsmith-api".
+    /// Takes precedence over scheme, host and port when set.
+    pub url: Option<String>,
+    pub scheme: String,
+    pub host: String,
+    pub port: u16,
+}
+
+impl Default for ApiConfig {
+    fn default() -> Self {
+        Self {
+            url: None,
+            scheme: String::from("http"),
+            host: String::from("localhost"),
+            port: 37240,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    pub host: String,
+    pub port: u16,
+    /// Directory for data the web app keeps itself, e.g. tag colors
+    pub data_dir: PathBuf,
+    /// Path prefix when served behind a reverse proxy, e.g. "/notes"
+    pub base_path: String,
+    /// Show error details on error pages, don't enable in production
+    pub debug: bool,
+    /// IANA timezone dates are shown in unless a user picks their own
+    pub timezone: String,
+}
+
+impl ServerConfig {
+    /// Checked when the config is loaded, so the fallback is never used
+    pub fn timezone(&self) -> Tz {
+        self.timezone.parse().unwrap_or(Tz::UTC)
+    }
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            host: String::from("0.0.0.0"),
+            port: 8080,
+            data_dir: PathBuf::from("./draftsmith_data"),
+            base_path: String::new(),
+            debug: false,
+            timezone: String::from("UTC"),
+        }
+    }
+}
+
+/// Sessions are kept in memory and lost on restart
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SessionConfig {
+    /// Only send the session cookie over HTTPS
+    pub secure: bool,
+    /// Expire sessions after this many minutes without a request
+    pub inactivity_minutes: Option<i64>,
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        Self {
+            secure: false,
+            inactivity_minutes: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LimitsConfig {
+    /// Largest request body accepted, mostly relevant to asset uploads
+    pub max_body_bytes: usize,
+}
+
+impl Default for LimitsConfig {
+    fn default() -> Self {
+        Self {
+            max_body_bytes: 1024 * 1024 * 1024, // 1 GB
+        }
+    }
+}
+
+/// HTTP basic auth in front of the whole app, enabled when both are set
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuthConfig {
+    pub username: Option<String>,
+    pub password: Option<String>,
+}
+
+impl AuthConfig {
+    pub fn credentials(&self) -> Option<(&str, &str)> {
+        match (&self.username, &self.password) {
+            (Some(username), Some(password)) => Some((username, password)),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CacheConfig {
+    /// max-age for proxied assets when the API doesn't send cache headers
+    pub asset_max_age: u64,
+    /// max-age for the bundled CSS, JS and fonts
+    pub static_max_age: u64,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            asset_max_age: 3600,
+            static_max_age: 604800,
+        }
+    }
+}
+
+/// Serve HTTPS directly, enabled when both cert and key are set.
+/// Sending SIGHUP reloads both files, e.g. after a certificate renewal.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TlsConfig {
+    /// PEM certificate chain
+    pub cert: Option<PathBuf>,
+    /// PEM private key
+    pub key: Option<PathBuf>,
+    /// Also listen for plain HTTP on this port and redirect it to HTTPS
+    pub redirect_http_port: Option<u16>,
+}
+
+impl TlsConfig {
+    pub fn files(&self) -> Option<(&PathBuf, &PathBuf)> {
+        match (&self.cert, &self.key) {
+            (Some(cert), Some(key)) => Some((cert, key)),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Human readable, one event over several lines
+    #[default]
+    Pretty,
+    /// One JSON object per line, for log collectors
+    Json,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogConfig {
+    pub format: LogFormat,
+    /// A tracing filter, e.g. "info" or "warn,draftsmith_axum_web_app=debug"
+    pub level: String,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            format: LogFormat::Pretty,
+            level: String::from("info"),
+        }
+    }
+}
+
+/// Flags for `serve`, each one overrides the config file and environment
+#[derive(Args, Debug)]
+pub struct ServeArgs {
+    /// Config file (default: ./draftsmith.toml if it exists)
+    #[arg(short = 'c', long, env = "DRAFTSMITH_CONFIG")]
+    pub config: Option<PathBuf>,
+
+    /// Print the effective configuration and exit
+    #[arg(long)]
+    pub print_config: bool,
+
+    /// Full base URL of the API, may include a path prefix,
+    /// e.g. "https://host/draftsmith-api" (instead of host, port and scheme)
+    #[arg(short = 'u', long, conflicts_with_all = ["api_host", "api_port", "api_scheme"])]
+    pub api_url: Option<String>,
+
+    /// Host for the API server (default: "localhost")
+    #[arg(short = 'S', long)]
+    pub api_host: Option<String>,
+
+    /// Port for the API server (default: 37240)
+    #[arg(short = 'P', long)]
+    pub api_port: Option<u16>,
+
+    /// Scheme for the API (http or https) (default: "http")
+    #[arg(short = 't', long)]
+    pub api_scheme: Option<String>,
+
+    /// Port for the Web App (default: 8080)
+    #[arg(short = 'p', long)]
+    pub port: Option<u16>,
+
+    /// Host for the Web App (default: "0.0.0.0")
+    #[arg(short = 's', long)]
+    pub host: Option<String>,
+
+    /// Directory for data the web app keeps itself, e.g. tag colors
+    #[arg(short = 'd', long)]
+    pub data_dir: Option<PathBuf>,
+
+    /// Path prefix when served behind a reverse proxy, e.g. "/notes"
+    #[arg(long)]
+    pub base_path: Option<String>,
+
+    /// Show error details on error pages, don't enable in production
+    #[arg(long)]
+    pub debug: bool,
+
+    /// Default display timezone, e.g. "Europe/Berlin" (default: "UTC")
+    #[arg(long)]
+    pub timezone: Option<String>,
+
+    /// Largest accepted request body in bytes
+    #[arg(long)]
+    pub max_body_bytes: Option<usize>,
+
+    /// Only send the session cookie over HTTPS,
+    /// `--secure-cookies=false` turns it off again
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    pub secure_cookies: Option<bool>,
+
+    /// PEM certificate chain, serves HTTPS together with --tls-key
+    #[arg(long)]
+    pub tls_cert: Option<PathBuf>,
+
+    /// PEM private key for --tls-cert
+    #[arg(long)]
+    pub tls_key: Option<PathBuf>,
+
+    /// Redirect plain HTTP on this port to HTTPS (needs TLS)
+    #[arg(long)]
+    pub tls_redirect_port: Option<u16>,
+
+    /// Log output format (default: pretty)
+    #[arg(long, value_enum)]
+    pub log_format: Option<LogFormat>,
+
+    /// Log filter, e.g. "debug" or "warn,tower_http=debug" (default: "info")
+    #[arg(long)]
+    pub log_level: Option<String>,
+}
+
+/// Check `--api-url` is an absolute http(s) URL and strip any trailing slash
+fn normalize_api_url(url: &str) -> Result<String, String> {
+    let parsed = Url::parse(url.trim()).map_err(|e| format!("Invalid API URL {:?}: {}", url, e))?;
+
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err(format!("API URL must use http or https, got {:?}", url));
+    }
+    if parsed.host_str().is_none() {
+        return Err(format!("API URL has no host: {:?}", url));
+    }
+    if parsed.query().is_some() || parsed.fragment().is_some() {
+        return Err(format!(
+            "API URL can't have a query string or fragment: {:?}",
+            url
+        ));
+    }
+
+    Ok(parsed.as_str().trim_end_matches('/').to_string())
+}
+
+/// Give the base path a leading slash and no trailing slash,
+/// so "" means the root and routes can be appended directly
+fn normalize_base_path(path: &str) -> Result<String, String> {
+    let trimmed = path.trim().trim_matches('/');
+    if trimmed.is_empty() {
+        return Ok(String::new());
+    }
+    if trimmed.contains(['?', '#', ':', '*']) || trimmed.split('/').any(|s| s.is_empty()) {
+        return Err(format!("Invalid base path: {:?}", path));
+    }
+    Ok(format!("/{}", trimmed))
+}
+
+/// Build an API URL from the base and path segments,
+/// percent-encoding each segment and keeping the base's path prefix.
+pub fn api_endpoint<'a>(
+    api_addr: &str,
+    segments: impl IntoIterator<Item = &'a str>,
+) -> Result<Url, String> {
+    let mut url = Url::parse(api_addr).map_err(|e| format!("Invalid API URL: {}", e))?;
+    url.path_segments_mut()
+        .map_err(|_| String::from("API URL can't be a base"))?
+        .pop_if_empty()
+        .extend(segments);
+    Ok(url)
+}
+
+fn parse_env<T: std::str::FromStr>(name: &str, value: String) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("Invalid value for {}: {:?}", name, value))
+}
+
+impl Config {
+    /// Build the effective configuration from all layers
+    pub fn load(args: &ServeArgs) -> Result<Self, String> {
+        let mut config = match &args.config {
+            Some(path) => Self::from_file(path)?,
+            None => {
+                let default_path = PathBuf::from(DEFAULT_CONFIG_FILE);
+                if default_path.exists() {
+                    Self::from_file(&default_path)?
+                } else {
+                    Self::default()
+                }
+            }
+        };
+
+        config.apply_env(|name| std::env::var(name).ok())?;
+        config.apply_args(args);
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    fn from_file(path: &std::path::Path) -> Result<Self, String> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| format!("Unable to read config file {}: {}", path.display(), e))?;
+        toml::from_str(&contents)
+            .map_err(|e| format!("Invalid config file {}: {}", path.display(), e))
+    }
+
+    /// `API_*` match the variables the justfile already sets,
+    /// everything else is prefixed with `DRAFTSMITH_`
+    fn apply_env(&mut self, var: impl Fn(&str) -> Option<String>) -> Result<(), String> {
+        // Host, port or scheme from here replace a URL from the file
+        if ["API_SCHEME", "API_HOST", "API_PORT"].iter().any(|name| var(name).is_some()) {
+            self.api.url = None;
+        }
+        if let Some(value) = var("API_URL") {
+            self.api.url = Some(value);
+        }
+        if let Some(value) = var("API_SCHEME") {
+            self.api.scheme = value;
+        }
+        if let Some(value) = var("API_HOST") {
+            self.api.host = value;
+        }
+        if let Some(value) = var("API_PORT") {
+            self.api.port = parse_env("API_PORT", value)?;
+        }
+        if let Some(value) = var("DRAFTSMITH_HOST") {
+            self.server.host = value;
+        }
+        if let Some(value) = var("DRAFTSMITH_PORT") {
+            self.server.port = parse_env("DRAFTSMITH_PORT", value)?;
+        }
+        if let Some(value) = var("DRAFTSMITH_DATA_DIR") {
+            self.server.data_dir = PathBuf::from(value);
+        }
+        if let Some(value) = var("DRAFTSMITH_BASE_PATH") {
+            self.server.base_path = value;
+        }
+        if let Some(value) = var("DRAFTSMITH_DEBUG") {
+            self.server.debug = parse_env("DRAFTSMITH_DEBUG", value)?;
+        }
+        if let Some(value) = var("DRAFTSMITH_TIMEZONE") {
+            self.server.timezone = value;
+        }
+        if let Some(value) = var("DRAFTSMITH_SESSION_SECURE") {
+            self.session.secure = parse_env("DRAFTSMITH_SESSION_SECURE", value)?;
+        }
+        if let Some(value) = var("DRAFTSMITH_SESSION_INACTIVITY_MINUTES") {
+            self.session.inactivity_minutes =
+                Some(parse_env("DRAFTSMITH_SESSION_INACTIVITY_MINUTES", value)?);
+        }
+        if let Some(value) = var("DRAFTSMITH_MAX_BODY_BYTES") {
+            self.limits.max_body_bytes = parse_env("DRAFTSMITH_MAX_BODY_BYTES", value)?;
+        }
+        if let Some(value) = var("DRAFTSMITH_AUTH_USERNAME") {
+            self.auth.username = Some(value);
+        }
+        if let Some(value) = var("DRAFTSMITH_AUTH_PASSWORD") {
+            self.auth.password = Some(value);
+        }
+        if let Some(value) = var("DRAFTSMITH_ASSET_MAX_AGE") {
+            self.cache.asset_max_age = parse_env("DRAFTSMITH_ASSET_MAX_AGE", value)?;
+        }
+        if let Some(value) = var("DRAFTSMITH_STATIC_MAX_AGE") {
+            self.cache.static_max_age = parse_env("DRAFTSMITH_STATIC_MAX_AGE", value)?;
+        }
+        if let Some(value) = var("DRAFTSMITH_LOG_FORMAT") {
+            self.log.format = LogFormat::from_str(&value, true)
+                .map_err(|_| format!("Invalid value for DRAFTSMITH_LOG_FORMAT: {:?}", value))?;
+        }
+        if let Some(value) = var("DRAFTSMITH_LOG_LEVEL") {
+            self.log.level = value;
+        }
+        if let Some(value) = var("DRAFTSMITH_TLS_CERT") {
+            self.tls.cert = Some(PathBuf::from(value));
+        }
+        if let Some(value) = var("DRAFTSMITH_TLS_KEY") {
+            self.tls.key = Some(PathBuf::from(value));
+        }
+        if let Some(value) = var("DRAFTSMITH_TLS_REDIRECT_PORT") {
+            self.tls.redirect_http_port = Some(parse_env("DRAFTSMITH_TLS_REDIRECT_PORT", value)?);
+        }
+        Ok(())
+    }
+
+    fn apply_args(&mut self, args: &ServeArgs) {
+        // Host, port or scheme flags replace a URL from the file or environment,
+        // clap already rejects them together with --api-url
+        if args.api_scheme.is_some() || args.api_host.is_some() || args.api_port.is_some() {
+            self.api.url = None;
+        }
+        if let Some(value) = &args.api_url {
+            self.api.url = Some(value.clone());
+        }
+        if let Some(value) = &args.api_scheme {
+            self.api.scheme = value.clone();
+        }
+        if let Some(value) = &args.api_host {
+            self.api.host = value.clone();
+        }
+        if let Some(value) = args.api_port {
+            self.api.port = value;
+        }
+        if let Some(value) = &args.host {
+            self.server.host = value.clone();
+        }
+        if let Some(value) = args.port {
+            self.server.port = value;
+        }
+        if let Some(value) = &args.data_dir {
+            self.server.data_dir = value.clone();
+        }
+        if let Some(value) = &args.base_path {
+            self.server.base_path = value.clone();
+        }
+        if args.debug {
+            self.server.debug = true;
+        }
+        if let Some(value) = &args.timezone {
+            self.server.timezone = value.clone();
+        }
+        if let Some(value) = args.max_body_bytes {
+            self.limits.max_body_bytes = value;
+        }
+        if let Some(value) = args.secure_cookies {
+            self.session.secure = value;
+        }
+        if let Some(value) = args.log_format {
+            self.log.format = value;
+        }
+        if let Some(value) = &args.log_level {
+            self.log.level = value.clone();
+        }
+        if let Some(value) = &args.tls_cert {
+            self.tls.cert = Some(value.clone());
+        }
+        if let Some(value) = &args.tls_key {
+            self.tls.key = Some(value.clone());
+        }
+        if let Some(value) = args.tls_redirect_port {
+            self.tls.redirect_http_port = Some(value);
+        }
+    }
+
+    fn validate(&mut self) -> Result<(), String> {
+        if !matches!(self.api.scheme.as_str(), "http" | "https") {
+            return Err(format!(
+                "API scheme must be http or https, got {:?}",
+                self.api.scheme
+            ));
+        }
+        // Also catches a host or port that doesn't make a valid URL
+        self.api.url = match &self.api.url {
+            Some(url) => Some(normalize_api_url(url)?),
+            None => {
+                normalize_api_url(&self.api_addr())?;
+                None
+            }
+        };
+        self.server.base_path = normalize_base_path(&self.server.base_path)?;
+        self.server
+            .timezone
+            .parse::<Tz>()
+            .map_err(|_| format!("Unknown timezone {:?}, expected e.g. \"Europe/Berlin\"", self.server.timezone))?;
+        if self.auth.username.is_some() != self.auth.password.is_some() {
+            return Err(String::from(
+                "Basic auth needs both a username and a password",
+            ));
+        }
+        EnvFilter::try_new(&self.log.level)
+            .map_err(|e| format!("Invalid log level {:?}: {}", self.log.level, e))?;
+        if self.tls.cert.is_some() != self.tls.key.is_some() {
+            return Err(String::from("TLS needs both a certificate and a key"));
+        }
+        if self.tls.files().is_some() {
+            // Cookies set over HTTPS should never be sent over plain HTTP
+            self.session.secure = true;
+        } else if self.tls.redirect_http_port.is_some() {
+            return Err(String::from(
+                "The HTTP to HTTPS redirect needs a TLS certificate and key",
+            ));
+        }
+        if self.tls.redirect_http_port == Some(self.server.port) {
+            return Err(String::from(
+                "The HTTP redirect port must differ from the HTTPS port",
+            ));
+        }
+        if let Some(minutes) = self.session.inactivity_minutes {
+            if minutes <= 0 {
+                return Err(String::from("Session inactivity timeout must be positive"));
+            }
+        }
+        Ok(())
+    }
+
+    /// Base URL of the API without a trailing slash, so the client's
+    /// `format!("{}/notes/...", api_addr)` calls keep any path prefix
+    pub fn api_addr(&self) -> String {
+        match &self.api.url {
+            Some(url) => url.clone(),
+            None => format!("{}://{}:{}", self.api.scheme, self.api.host, self.api.port),
+        }
+    }
+
+    pub fn bind_addr(&self) -> String {
+        format!("{}:{}", self.server.host, self.server.port)
+    }
+
+    /// The effective configuration as TOML, with secrets masked
+    pub fn to_printable_toml(&self) -> String {
+        let mut printable = self.clone();
+        if printable.auth.password.is_some() {
+            printable.auth.password = Some(String::from("********"));
+        }
+        toml::to_string_pretty(&printable)
+            .unwrap_or_else(|e| format!("# Unable to print config: {}", e))
+    }
+}
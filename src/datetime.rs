@@ -0,0 +1,196 @@
+//! Timestamps from the Draftsmith API, parsed with chrono and shown
+//! in the reader's timezone.
+use chrono::{DateTime, NaiveDateTime, Utc};
+use chrono_tz::Tz;
+use minijinja::{Error, State, Value};
+
+/// Shown as e.g. "2024-11-22 05:54"
+const ABSOLUTE_FORMAT: &str = "%Y-%m-%d %H:%M";
+
+/// The timezone a user picked, if it's valid, otherwise the server's
+pub fn display_timezone(user_timezone: Option<&str>, server_timezone: Tz) -> Tz {
+    user_timezone
+        .and_then(|name| name.parse().ok())
+        .unwrap_or(server_timezone)
+}
+
+/// The API sends e.g. "2024-11-22T04:54:33.933017" without an offset,
+/// which is UTC. Values with an offset are accepted as well.
+pub fn parse_timestamp(value: &str) -> Option<DateTime<Utc>> {
+    if let Ok(datetime) = DateTime::parse_from_rfc3339(value) {
+        return Some(datetime.with_timezone(&Utc));
+    }
+    NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S%.f")
+        .ok()
+        .map(|naive| naive.and_utc())
+}
+
+/// Sections of a list ordered by time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DayGroup {
+    Today,
+    Yesterday,
+    ThisWeek,
+    Earlier,
+}
+
+impl DayGroup {
+    /// Days are counted in `timezone`, so "Today" starts at the reader's midnight
+    pub fn of(timestamp: DateTime<Utc>, now: DateTime<Utc>, timezone: Tz) -> Self {
+        let today = now.with_timezone(&timezone).date_naive();
+        let day = timestamp.with_timezone(&timezone).date_naive();
+        match (today - day).num_days() {
+            // Clocks disagree now and then, the future is today
+            ..=0 => Self::Today,
+            1 => Self::Yesterday,
+            2..=6 => Self::ThisWeek,
+            _ => Self::Earlier,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Today => "Today",
+            Self::Yesterday => "Yesterday",
+            Self::ThisWeek => "This week",
+            Self::Earlier => "Earlier",
+        }
+    }
+}
+
+/// e.g. "just now", "5 minutes ago", "3 days ago"
+pub fn relative_time(timestamp: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    let seconds = (now - timestamp).num_seconds();
+    if seconds < 60 {
+        return String::from("just now");
+    }
+
+    let (amount, unit) = match seconds {
+        ..=3_599 => (seconds / 60, "minute"),
+        ..=86_399 => (seconds / 3_600, "hour"),
+        ..=2_591_999 => (seconds / 86_400, "day"),
+        ..=31_535_999 => (seconds / 2_592_000, "month"),
+        _ => (seconds / 31_536_000, "year"),
+    };
+    let plural = if amount == 1 { "" } else { "s" };
+    format!("{} {}{} ago", amount, unit, plural)
+}
+
+/// The display timezone of the page being rendered, see `BodyTemplateContext`.
+/// Pages rendered without it, e.g. bare error pages, fall back to UTC.
+fn render_timezone(state: &State) -> Tz {
+    let name = state.lookup("display_timezone");
+    display_timezone(name.as_ref().and_then(|v| v.as_str()), Tz::UTC)
+}
+
+fn time_element(timestamp: DateTime<Utc>, text: &str, title: &str) -> Value {
+    Value::from_safe_string(format!(
+        r#"<time datetime="{}" title="{}">{}</time>"#,
+        timestamp.to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+        html_escape::encode_double_quoted_attribute(title),
+        html_escape::encode_text(text),
+    ))
+}
+
+/// `{{ note.modified_at | datetime }}`, or with a chrono format string,
+/// e.g. `{{ note.created_at | datetime("%d %b %Y") }}`.
+///
+/// Values that aren't timestamps are passed through unchanged.
+pub fn absolute_filter(state: &State, value: Value, format: Option<&str>) -> Result<Value, Error> {
+    let Some(timestamp) = value.as_str().and_then(parse_timestamp) else {
+        return Ok(value);
+    };
+    let local = timestamp.with_timezone(&render_timezone(state));
+    let text = local.format(format.unwrap_or(ABSOLUTE_FORMAT)).to_string();
+    let title = local.format("%Y-%m-%d %H:%M:%S %Z").to_string();
+    Ok(time_element(timestamp, &text, &title))
+}
+
+/// `{{ note.modified_at | timeago }}`, e.g. "5 minutes ago",
+/// with the absolute time as a tooltip
+pub fn relative_filter(state: &State, value: Value) -> Result<Value, Error> {
+    let Some(timestamp) = value.as_str().and_then(parse_timestamp) else {
+        return Ok(value);
+    };
+    let local = timestamp.with_timezone(&render_timezone(state));
+    let title = local.format("%Y-%m-%d %H:%M %Z").to_string();
+    Ok(time_element(timestamp, &relative_time(timestamp, Utc::now()), &title))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone};
+
+    fn utc(y: i32, m: u32, d: u32, h: u32, min: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(y, m, d, h, min, 0).unwrap()
+    }
+
+    #[test]
+    fn parses_api_timestamps_as_utc() {
+        assert_eq!(
+            parse_timestamp("2024-11-22T04:54:33.933017"),
+            Some(utc(2024, 11, 22, 4, 54) + Duration::microseconds(33_933_017))
+        );
+        assert_eq!(
+            parse_timestamp("2024-11-22T05:54:00+01:00"),
+            Some(utc(2024, 11, 22, 4, 54))
+        );
+        assert_eq!(parse_timestamp("yesterday"), None);
+    }
+
+    #[test]
+    fn day_groups_follow_calendar_days() {
+        let now = utc(2024, 11, 22, 12, 0);
+        let group = |t| DayGroup::of(t, now, Tz::UTC);
+
+        assert_eq!(group(utc(2024, 11, 22, 0, 0)), DayGroup::Today);
+        assert_eq!(group(utc(2024, 11, 21, 23, 59)), DayGroup::Yesterday);
+        assert_eq!(group(utc(2024, 11, 21, 0, 0)), DayGroup::Yesterday);
+        assert_eq!(group(utc(2024, 11, 20, 23, 59)), DayGroup::ThisWeek);
+        assert_eq!(group(utc(2024, 11, 16, 0, 0)), DayGroup::ThisWeek);
+        assert_eq!(group(utc(2024, 11, 15, 23, 59)), DayGroup::Earlier);
+        // Clock skew puts notes slightly in the future
+        assert_eq!(group(utc(2024, 11, 23, 1, 0)), DayGroup::Today);
+    }
+
+    #[test]
+    fn day_groups_use_the_readers_midnight() {
+        // 23:30 UTC is already the next day in Berlin, but not in New York
+        let now = utc(2024, 11, 22, 23, 30);
+        let earlier = utc(2024, 11, 22, 22, 0);
+        let berlin: Tz = "Europe/Berlin".parse().unwrap();
+        let new_york: Tz = "America/New_York".parse().unwrap();
+
+        assert_eq!(DayGroup::of(earlier, now, berlin), DayGroup::Yesterday);
+        assert_eq!(DayGroup::of(earlier, now, new_york), DayGroup::Today);
+        assert_eq!(DayGroup::of(earlier, now, Tz::UTC), DayGroup::Today);
+    }
+
+    #[test]
+    fn relative_times_round_down() {
+        let now = utc(2024, 11, 22, 12, 0);
+        let ago = |seconds| relative_time(now - Duration::seconds(seconds), now);
+
+        assert_eq!(ago(0), "just now");
+        assert_eq!(ago(59), "just now");
+        assert_eq!(ago(60), "1 minute ago");
+        assert_eq!(ago(3_599), "59 minutes ago");
+        assert_eq!(ago(3_600), "1 hour ago");
+        assert_eq!(ago(86_399), "23 hours ago");
+        assert_eq!(ago(86_400), "1 day ago");
+        assert_eq!(ago(2_592_000), "1 month ago");
+        assert_eq!(ago(31_536_000), "1 year ago");
+        assert_eq!(ago(3 * 31_536_000), "3 years ago");
+        // Future timestamps don't produce negative amounts
+        assert_eq!(ago(-120), "just now");
+    }
+
+    #[test]
+    fn display_timezone_falls_back_to_the_servers() {
+        let berlin: Tz = "Europe/Berlin".parse().unwrap();
+        assert_eq!(display_timezone(None, berlin), berlin);
+        assert_eq!(display_timezone(Some("Not/A_Zone"), berlin), berlin);
+        assert_eq!(display_timezone(Some("Asia/Tokyo"), berlin), Tz::Asia__Tokyo);
+    }
+}
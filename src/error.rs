@@ -0,0 +1,151 @@
+use crate::templates::ENV;
+use axum::http::StatusCode;
+use axum::response::{Html, IntoResponse, Response};
+use minijinja::{context, Value};
+use std::error::Error;
+use std::fmt::Debug;
+use std::sync::OnceLock;
+use tracing::{error, info};
+
+/// Set once at startup from `server.debug`
+static DEBUG: OnceLock<bool> = OnceLock::new();
+
+/// Show the error chain on error pages, only meant for development
+pub fn set_debug(debug: bool) {
+    let _ = DEBUG.set(debug);
+}
+
+fn debug_enabled() -> bool {
+    DEBUG.get().copied().unwrap_or(false)
+}
+
+/// The `reqwest` error behind an API client error, if there is one
+fn reqwest_error<'a>(err: &'a (dyn Error + 'static)) -> Option<&'a reqwest::Error> {
+    let mut source = Some(err);
+    while let Some(err) = source {
+        if let Some(err) = err.downcast_ref::<reqwest::Error>() {
+            return Some(err);
+        }
+        source = err.source();
+    }
+    None
+}
+
+/// Whether an API client error means the item doesn't exist,
+/// as opposed to the API being unreachable or failing
+pub fn is_not_found(err: &(dyn Error + 'static)) -> bool {
+    reqwest_error(err).and_then(|e| e.status()) == Some(StatusCode::NOT_FOUND)
+}
+
+/// An error a handler can return, rendered as a themed error page.
+///
+/// The message is shown to the user, the chain only in debug mode.
+#[derive(Debug)]
+pub struct AppError {
+    status: StatusCode,
+    message: String,
+    chain: Vec<String>,
+    /// Layout context, so the page keeps the sidebar and navbar
+    layout: Option<Value>,
+}
+
+impl AppError {
+    pub fn new(status: StatusCode, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            message: message.into(),
+            chain: Vec::new(),
+            layout: None,
+        }
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::NOT_FOUND, message)
+    }
+
+    /// A failed call to the Draftsmith API.
+    ///
+    /// Not found and timeouts are told apart by the `reqwest` error
+    /// somewhere in the client error's source chain.
+    pub fn upstream(
+        message: impl Into<String>,
+        err: impl Into<Box<dyn Error + Send + Sync>>,
+    ) -> Self {
+        let err = err.into();
+        let status = match reqwest_error(&*err) {
+            Some(e) if e.status() == Some(StatusCode::NOT_FOUND) => StatusCode::NOT_FOUND,
+            Some(e) if e.is_timeout() => StatusCode::GATEWAY_TIMEOUT,
+            _ => StatusCode::BAD_GATEWAY,
+        };
+        Self::new(status, message).with_cause(err)
+    }
+
+    /// Use a more specific message when the API said the item doesn't exist
+    pub fn or_not_found(mut self, message: impl Into<String>) -> Self {
+        if self.status == StatusCode::NOT_FOUND {
+            self.message = message.into();
+        }
+        self
+    }
+
+    /// Render inside the usual layout instead of a bare page
+    pub fn with_layout(mut self, layout: Value) -> Self {
+        self.layout = Some(layout);
+        self
+    }
+
+    fn with_cause(mut self, err: impl Debug) -> Self {
+        self.chain.push(format!("{:#?}", err));
+        self
+    }
+}
+
+impl From<minijinja::Error> for AppError {
+    fn from(err: minijinja::Error) -> Self {
+        let mut chain = vec![format!("{:#}", err)];
+        let mut source = std::error::Error::source(&err);
+        while let Some(next_err) = source {
+            chain.push(next_err.to_string());
+            source = next_err.source();
+        }
+        Self {
+            status: StatusCode::INTERNAL_SERVER_ERROR,
+            message: String::from("This page could not be rendered"),
+            chain,
+            layout: None,
+        }
+    }
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        if self.status.is_server_error() {
+            error!(status = self.status.as_u16(), message = %self.message, chain = ?self.chain, "Request failed");
+        } else {
+            info!(status = self.status.as_u16(), message = %self.message, "Request failed");
+        }
+
+        let error_ctx = context! {
+            status => self.status.as_u16(),
+            reason => self.status.canonical_reason().unwrap_or("Error"),
+            message => self.message,
+            chain => if debug_enabled() { self.chain } else { Vec::new() },
+        };
+        let ctx = match self.layout {
+            Some(layout) => context! { ..layout, ..error_ctx },
+            None => error_ctx,
+        };
+
+        let body = ENV
+            .get_template("body/error.html")
+            .and_then(|template| template.render(ctx));
+        match body {
+            Ok(html) => (self.status, Html(html)).into_response(),
+            Err(e) => {
+                // The error page itself is broken, fall back to plain text
+                error!(error = %e, "Could not render the error page");
+                (self.status, self.status.to_string()).into_response()
+            }
+        }
+    }
+}
@@ -1,3 +1,4 @@
+use crate::metrics;
 use axum::async_trait;
 use serde::{Deserialize, Serialize};
 use tower_sessions::{session::Error as SessionError, Session};
@@ -47,10 +48,19 @@ pub trait FlashMessageStore {
 #[async_trait]
 impl FlashMessageStore for Session {
     async fn set_flash(&self, message: FlashMessage) -> Result<(), SessionError> {
-        self.insert("flash", message).await
+        let value = serde_json::to_value(message).map_err(SessionError::SerdeJson)?;
+        // A new message replaces one that hasn't been shown yet
+        if self.insert_value("flash", value).await?.is_none() {
+            metrics::flash_queued();
+        }
+        Ok(())
     }
 
     async fn take_flash(&self) -> Result<Option<FlashMessage>, SessionError> {
-        self.remove::<FlashMessage>("flash").await
+        let message = self.remove::<FlashMessage>("flash").await?;
+        if message.is_some() {
+            metrics::flash_taken();
+        }
+        Ok(message)
     }
 }
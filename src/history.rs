@@ -0,0 +1,76 @@
+use axum::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tower_sessions::{session::Error as SessionError, Session};
+
+/// Views kept per session, the oldest are dropped first
+pub const MAX_HISTORY: usize = 200;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub note_id: i32,
+    pub title: String,
+    pub viewed_at: DateTime<Utc>,
+}
+
+/// The notes a user opened, oldest first, kept in their session
+#[async_trait]
+pub trait ViewHistory {
+    async fn history(&self) -> Result<Vec<HistoryEntry>, SessionError>;
+    async fn record_view(&self, note_id: i32, title: &str) -> Result<(), SessionError>;
+    async fn clear_history(&self) -> Result<(), SessionError>;
+}
+
+#[async_trait]
+impl ViewHistory for Session {
+    async fn history(&self) -> Result<Vec<HistoryEntry>, SessionError> {
+        Ok(self.get("history").await?.unwrap_or_default())
+    }
+
+    async fn record_view(&self, note_id: i32, title: &str) -> Result<(), SessionError> {
+        let mut history = self.history().await?;
+
+        // Reloading a note isn't a new step in the history
+        if history.last().is_some_and(|last| last.note_id == note_id) {
+            history.pop();
+        }
+        history.push(HistoryEntry {
+            note_id,
+            title: title.to_string(),
+            viewed_at: Utc::now(),
+        });
+        if history.len() > MAX_HISTORY {
+            let excess = history.len() - MAX_HISTORY;
+            history.drain(..excess);
+        }
+
+        self.insert("history", history).await
+    }
+
+    async fn clear_history(&self) -> Result<(), SessionError> {
+        self.remove_value("history").await.map(|_| ())
+    }
+}
+
+/// The note viewed just before or after the latest view of `from`.
+///
+/// Without `from`, e.g. on a page that isn't a note, going back
+/// lands on the most recently viewed note.
+pub fn step(history: &[HistoryEntry], from: Option<i32>, forward: bool) -> Option<i32> {
+    let Some(position) = from.and_then(|id| history.iter().rposition(|e| e.note_id == id)) else {
+        return if forward {
+            None
+        } else {
+            history.last().map(|e| e.note_id)
+        };
+    };
+
+    let target = if forward {
+        position.checked_add(1)
+    } else {
+        position.checked_sub(1)
+    };
+    target
+        .and_then(|index| history.get(index))
+        .map(|e| e.note_id)
+}
@@ -1,21 +1,51 @@
+//! Sidebar tree data, rendered by the macros in `templates/tree/macros.html`.
+use crate::urls::url_for;
 use draftsmith_rest_api::client::NoteTreeNode;
-use std::fmt::Write;
-use std::sync::Mutex;
-use std::sync::OnceLock;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::ops::Range;
+
+/// Per-request state for rendering the sidebar tree.
+///
+/// Built for every request, so one user's current note never decides
+/// which `<details>` are open in another user's sidebar.
+#[derive(Debug, Clone, Default)]
+pub struct TreeRenderContext {
+    pub current_note_id: Option<i32>,
+    /// Notes to render expanded, e.g. the ones the user left open
+    pub open_ids: HashSet<i32>,
+    /// Notes matching the sidebar filter, highlighted when rendered
+    pub matched_ids: HashSet<i32>,
+}
 
-static CURRENT_BREADCRUMBS: OnceLock<Mutex<Vec<i32>>> = OnceLock::new();
+impl TreeRenderContext {
+    pub fn new(current_note_id: Option<i32>) -> Self {
+        Self {
+            current_note_id,
+            open_ids: HashSet::new(),
+            matched_ids: HashSet::new(),
+        }
+    }
 
-fn get_breadcrumbs() -> &'static Mutex<Vec<i32>> {
-    CURRENT_BREADCRUMBS.get_or_init(|| Mutex::new(Vec::new()))
-}
+    pub fn with_open_ids(mut self, ids: impl IntoIterator<Item = i32>) -> Self {
+        self.open_ids.extend(ids);
+        self
+    }
+
+    fn is_current(&self, node_id: i32) -> bool {
+        self.current_note_id == Some(node_id)
+    }
 
-pub fn set_breadcrumbs(breadcrumbs: Vec<i32>) {
-    if let Ok(mut crumbs) = get_breadcrumbs().lock() {
-        *crumbs = breadcrumbs;
+    pub fn should_be_open(&self, node: &NoteTreeNode) -> bool {
+        self.open_ids.contains(&node.id)
+            || self.is_current(node.id)
+            || self
+                .current_note_id
+                .is_some_and(|current| is_parent_of_current(node, current))
     }
 }
 
-fn is_parent_of_current(node_id: i32, node: &NoteTreeNode, current_note_id: i32) -> bool {
+fn is_parent_of_current(node: &NoteTreeNode, current_note_id: i32) -> bool {
     // Check direct children
     if node
         .children
@@ -26,217 +56,339 @@ fn is_parent_of_current(node_id: i32, node: &NoteTreeNode, current_note_id: i32)
     }
 
     // Recursively check children
-    for child in &node.children {
-        if is_parent_of_current(node_id, child, current_note_id) {
-            return true;
-        }
-    }
-
-    false
+    node.children
+        .iter()
+        .any(|child| is_parent_of_current(child, current_note_id))
 }
 
-pub fn should_be_open(node_id: i32, current_note_id: i32, node: &NoteTreeNode) -> bool {
-    if let Ok(crumbs) = get_breadcrumbs().lock() {
-        crumbs.contains(&node_id)
-            || current_note_id == node_id
-            || is_parent_of_current(node_id, node, current_note_id)
-    } else {
-        false // fallback if lock fails
-    }
+/// One sidebar entry as the tree templates see it
+#[derive(Debug, Clone, Serialize)]
+pub struct TreeNodeView {
+    pub id: i32,
+    pub title: String,
+    /// 0 for top-level notes
+    pub depth: usize,
+    pub open: bool,
+    pub current: bool,
+    pub matched: bool,
+    pub has_children: bool,
+    /// Where the tree controller loads the children of a collapsed node
+    pub children_url: Option<String>,
+    /// Only filled in for open nodes, collapsed ones are loaded on demand
+    pub children: Vec<TreeNodeView>,
 }
 
-fn build_details(node_id: i32, current_note_id: Option<i32>, node: &NoteTreeNode) -> String {
-    let open_status = if should_be_open(node_id, current_note_id.unwrap_or(-1), node) {
-        " open"
+fn node_view(node: &NoteTreeNode, render: &TreeRenderContext, depth: usize) -> TreeNodeView {
+    let open = render.should_be_open(node);
+    let has_children = !node.children.is_empty();
+
+    let children_url = (has_children && !open).then(|| {
+        let url = url_for(&format!("/tree/children/{}", node.id));
+        match render.current_note_id {
+            Some(current) => format!("{}?current={}", url, current),
+            None => url,
+        }
+    });
+    let children = if open {
+        node_views(&node.children, render, depth + 1)
     } else {
-        ""
+        Vec::new()
     };
 
-    format!(r#"<details{}>"#, open_status,)
+    TreeNodeView {
+        id: node.id,
+        title: node.title.clone().unwrap_or_else(|| String::from("Untitled")),
+        depth,
+        open,
+        current: render.is_current(node.id),
+        matched: render.matched_ids.contains(&node.id),
+        has_children,
+        children_url,
+        children,
+    }
 }
 
-pub struct TreePage {
-    content: String,
-    item_count: usize,
+fn node_views(nodes: &[NoteTreeNode], render: &TreeRenderContext, depth: usize) -> Vec<TreeNodeView> {
+    nodes
+        .iter()
+        .map(|node| node_view(node, render, depth))
+        .collect()
 }
 
-pub fn build_note_tree_html(
-    tree: Vec<NoteTreeNode>,
+/// Prune the tree to notes whose titles contain `query`, ignoring case,
+/// keeping their ancestors so they stay in place.
+///
+/// The returned context opens every kept ancestor and marks the matches.
+pub fn filter_tree(
+    tree: &[NoteTreeNode],
+    query: &str,
     current_note_id: Option<i32>,
-    parent_ids: Vec<i32>,
-    max_items_per_page: usize,
-) -> Vec<String> {
-    let mut pages = Vec::new();
-    let mut current_page = TreePage {
-        content: String::new(),
-        item_count: 0,
-    };
-
-    if max_items_per_page == 0 {
-        return Vec::new();
-    }
+) -> (Vec<NoteTreeNode>, TreeRenderContext) {
+    let query = query.to_lowercase();
+    let mut render = TreeRenderContext::new(current_note_id);
+    let filtered = filter_nodes(tree, &query, &mut render);
+    (filtered, render)
+}
 
-    // Start first page
-    write!(
-        current_page.content,
-        r#"<ul class="menu bg-base-200 rounded-box w-full md:w-56" data-controller="tree">"#
-    )
-    .unwrap();
+fn filter_nodes(
+    nodes: &[NoteTreeNode],
+    query: &str,
+    render: &mut TreeRenderContext,
+) -> Vec<NoteTreeNode> {
+    nodes
+        .iter()
+        .filter_map(|node| {
+            let children = filter_nodes(&node.children, query, render);
+            let matched = node
+                .title
+                .as_deref()
+                .is_some_and(|title| title.to_lowercase().contains(query));
+            if !matched && children.is_empty() {
+                return None;
+            }
 
-    // Track ancestry for context when splitting pages
-    let mut ancestry = Vec::new();
+            if matched {
+                render.matched_ids.insert(node.id);
+            }
+            if !children.is_empty() {
+                render.open_ids.insert(node.id);
+            }
+            let mut kept = node.clone();
+            kept.children = children;
+            Some(kept)
+        })
+        .collect()
+}
 
-    for node in &tree {
-        render_node_with_paging(
-            node,
-            current_note_id,
-            &parent_ids,
-            -1,
-            4,
-            &mut current_page,
-            &mut pages,
-            max_items_per_page,
-            &mut ancestry,
-        );
+/// Find a note anywhere in the tree, along with its depth
+pub fn find_node(tree: &[NoteTreeNode], id: i32) -> Option<(&NoteTreeNode, usize)> {
+    fn find_at<'a>(
+        tree: &'a [NoteTreeNode],
+        id: i32,
+        depth: usize,
+    ) -> Option<(&'a NoteTreeNode, usize)> {
+        tree.iter().find_map(|node| {
+            if node.id == id {
+                Some((node, depth))
+            } else {
+                find_at(&node.children, id, depth + 1)
+            }
+        })
     }
+    find_at(tree, id, 0)
+}
 
-    // Close final page if it has content
-    if current_page.item_count > 0 {
-        current_page.content.push_str("</ul>");
-        pages.push(current_page.content);
+/// The children of a note at `depth`, loaded into the sidebar
+/// when a collapsed `<details>` is expanded
+pub fn build_children(node: &NoteTreeNode, depth: usize, render: &TreeRenderContext) -> Vec<TreeNodeView> {
+    node_views(&node.children, render, depth + 1)
+}
+
+/// Split the top-level notes into sidebar pages of about `page_size`
+/// rendered items, returning the range of roots on each page.
+///
+/// Pages only break between top-level subtrees, so every page is a
+/// complete list. A subtree bigger than a page gets a page of its own.
+pub fn paginate_tree(
+    tree: &[NoteTreeNode],
+    render: &TreeRenderContext,
+    page_size: usize,
+) -> Vec<Range<usize>> {
+    let mut pages = Vec::new();
+    let mut start = 0;
+    let mut item_count = 0;
+
+    for (index, node) in tree.iter().enumerate() {
+        let items = rendered_items(node, render);
+        if item_count > 0 && item_count + items > page_size {
+            pages.push(start..index);
+            start = index;
+            item_count = 0;
+        }
+        item_count += items;
+    }
+    if start < tree.len() {
+        pages.push(start..tree.len());
     }
 
     pages
 }
 
-fn render_node_with_paging(
-    node: &NoteTreeNode,
-    current_note_id: Option<i32>,
-    parent_ids: &[i32],
-    levels_below_current: i32,
-    max_levels_below: i32,
-    current_page: &mut TreePage,
-    pages: &mut Vec<String>,
-    max_items: usize,
-    ancestry: &mut Vec<NoteTreeNode>,
-) {
-    // Check if we need to start a new page
-    if current_page.item_count >= max_items {
-        // Close all open tags properly
-        current_page.content.push_str("</details></li></ul>");
-        pages.push(std::mem::take(&mut current_page.content));
-
-        // Start new page
-        write!(
-            current_page.content,
-            r#"<ul class="menu bg-base-200 rounded-box w-full md:w-56" data-controller="tree">"#
-        )
-        .unwrap();
-
-        // Reset count and add ancestry context
-        current_page.item_count = 0;
-
-        // Add ancestor nodes as context with proper nesting
-        for (i, ancestor) in ancestry.iter().enumerate() {
-            if i > 0 {
-                current_page.content.push_str("<ul>");
-            }
-            render_context_node(current_page, ancestor, current_note_id);
-        }
+/// How many `<li>` a subtree renders to, collapsed children aren't rendered
+fn rendered_items(node: &NoteTreeNode, render: &TreeRenderContext) -> usize {
+    if render.should_be_open(node) {
+        1 + node
+            .children
+            .iter()
+            .map(|child| rendered_items(child, render))
+            .sum::<usize>()
+    } else {
+        1
     }
+}
 
-    // Push current node to ancestry before processing children
-    ancestry.push(node.clone());
+/// The 1-indexed page whose subtrees contain `note_id`
+pub fn page_for_note(tree: &[NoteTreeNode], pages: &[Range<usize>], note_id: i32) -> Option<usize> {
+    pages
+        .iter()
+        .position(|range| find_node(&tree[range.clone()], note_id).is_some())
+        .map(|index| index + 1)
+}
 
-    // Render current node
-    render_single_node(current_page, node, current_note_id);
-    current_page.item_count += 1;
+/// The whole tree on one page, e.g. once it's been filtered
+pub fn build_tree(tree: &[NoteTreeNode], render: &TreeRenderContext) -> Vec<TreeNodeView> {
+    node_views(tree, render, 0)
+}
+
+/// The top-level notes of each page from `paginate_tree`
+pub fn build_tree_pages(
+    tree: &[NoteTreeNode],
+    pages: &[Range<usize>],
+    render: &TreeRenderContext,
+) -> Vec<Vec<TreeNodeView>> {
+    pages
+        .iter()
+        .map(|range| build_tree(&tree[range.clone()], render))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::templates::ENV;
+    use minijinja::context;
+    use std::sync::Barrier;
+
+    fn node(id: i32, children: Vec<serde_json::Value>) -> serde_json::Value {
+        serde_json::json!({ "id": id, "title": format!("Note {}", id), "children": children })
+    }
+
+    /// 1 > 2 > 3 and 4 > 5
+    fn sample_tree() -> Vec<NoteTreeNode> {
+        serde_json::from_value(serde_json::json!([
+            node(1, vec![node(2, vec![node(3, vec![])])]),
+            node(4, vec![node(5, vec![])]),
+        ]))
+        .expect("sample tree")
+    }
 
-    // Process children if any
-    if !node.children.is_empty() {
-        current_page.content.push_str("<ul>");
-        for child in &node.children {
-            let next_level = if current_note_id == Some(node.id) {
-                0
-            } else if levels_below_current >= 0 {
-                levels_below_current + 1
+    fn find_view(views: &[TreeNodeView], id: i32) -> Option<&TreeNodeView> {
+        views.iter().find_map(|view| {
+            if view.id == id {
+                Some(view)
             } else {
-                -1
-            };
-
-            render_node_with_paging(
-                child,
-                current_note_id,
-                parent_ids,
-                next_level,
-                max_levels_below,
-                current_page,
-                pages,
-                max_items,
-                ancestry,
-            );
-        }
-        current_page.content.push_str("</ul>");
+                find_view(&view.children, id)
+            }
+        })
     }
 
-    // Close the current node's tags
-    current_page.content.push_str("</details></li>");
+    #[test]
+    fn concurrent_renders_keep_their_own_open_state() {
+        let tree = sample_tree();
+        let barrier = Barrier::new(2);
+
+        let render = |current: i32, other_root: i32| {
+            barrier.wait();
+            for _ in 0..100 {
+                let ctx = TreeRenderContext::new(Some(current));
+                let views = build_tree(&tree, &ctx);
+
+                let current_view = find_view(&views, current).expect("current note rendered");
+                assert!(current_view.current);
+                assert!(current_view.open);
+                let other = find_view(&views, other_root).expect("other root rendered");
+                assert!(!other.open, "note {} opened by another request", other_root);
+                assert!(other.children.is_empty());
+
+                let html = ENV
+                    .get_template("tree/filter.html")
+                    .and_then(|t| t.render(context! { nodes => views, query => "" }))
+                    .expect("tree renders");
+                assert_eq!(html.matches("bg-blue-100").count(), 1);
+                assert!(html.contains(&format!(
+                    r#"class="note-item bg-blue-100 text-blue-800 rounded-md" draggable="true" data-note-id="{}""#,
+                    current
+                )));
+            }
+        };
 
-    // Remove current node from ancestry after processing
-    ancestry.pop();
-}
+        std::thread::scope(|scope| {
+            scope.spawn(|| render(3, 4));
+            scope.spawn(|| render(5, 1));
+        });
+    }
 
-fn render_single_node(page: &mut TreePage, node: &NoteTreeNode, current_note_id: Option<i32>) {
-    let class_str = if Some(node.id) == current_note_id {
-        r#"note-item bg-blue-100 text-blue-800 rounded-md"#
-    } else {
-        "note-item"
-    };
+    #[test]
+    fn ancestors_of_the_current_note_are_open() {
+        let tree = sample_tree();
+        let ctx = TreeRenderContext::new(Some(3));
+        let views = build_tree(&tree, &ctx);
+
+        assert!(find_view(&views, 1).unwrap().open);
+        assert!(find_view(&views, 2).unwrap().open);
+        // Collapsed notes with children are loaded on demand
+        let collapsed = find_view(&views, 4).unwrap();
+        assert!(!collapsed.open);
+        assert_eq!(
+            collapsed.children_url.as_deref(),
+            Some("/tree/children/4?current=3")
+        );
+    }
 
-    write!(
-        page.content,
-        r#"<li class="{}" draggable="true" data-note-id="{}">"#,
-        class_str, node.id
-    )
-    .unwrap();
+    fn leaves(ids: std::ops::RangeInclusive<i32>) -> Vec<NoteTreeNode> {
+        serde_json::from_value(serde_json::Value::Array(
+            ids.map(|id| node(id, vec![])).collect(),
+        ))
+        .expect("leaves")
+    }
 
-    let details = build_details(node.id, current_note_id, node);
+    #[test]
+    fn collapsed_roots_fill_pages_evenly() {
+        let tree = leaves(1..=5);
+        let pages = paginate_tree(&tree, &TreeRenderContext::new(None), 2);
+        assert_eq!(pages, vec![0..2, 2..4, 4..5]);
+    }
 
-    let title = node.title.as_deref().unwrap_or("Untitled");
-    let summary_class = if Some(node.id) == current_note_id {
-        "font-semibold"
-    } else {
-        ""
-    };
+    #[test]
+    fn open_subtrees_count_every_rendered_note() {
+        let tree = sample_tree();
+        // Collapsed, both roots fit on one page
+        assert_eq!(paginate_tree(&tree, &TreeRenderContext::new(None), 2), vec![0..2]);
+        // With 3 open, the first root renders 1, 2 and 3
+        assert_eq!(
+            paginate_tree(&tree, &TreeRenderContext::new(Some(3)), 3),
+            vec![0..1, 1..2]
+        );
+        // Expanded by the user rather than by the current note
+        let render = TreeRenderContext::new(None).with_open_ids([4]);
+        assert_eq!(paginate_tree(&tree, &render, 2), vec![0..1, 1..2]);
+    }
+
+    #[test]
+    fn oversize_subtrees_get_a_page_of_their_own() {
+        let tree: Vec<NoteTreeNode> = serde_json::from_value(serde_json::json!([
+            node(10, vec![]),
+            node(1, vec![node(2, vec![node(3, vec![])])]),
+            node(11, vec![]),
+        ]))
+        .expect("tree");
+        let render = TreeRenderContext::new(Some(3));
+
+        // The open subtree renders 3 notes, more than a page holds,
+        // but a subtree is never split across pages or dropped
+        let pages = paginate_tree(&tree, &render, 2);
+        assert_eq!(pages, vec![0..1, 1..2, 2..3]);
+        assert_eq!(page_for_note(&tree, &pages, 3), Some(2));
+        assert_eq!(page_for_note(&tree, &pages, 11), Some(3));
+        assert_eq!(page_for_note(&tree, &pages, 99), None);
+
+        // Every top-level note is on exactly one page
+        let covered: usize = pages.iter().map(|range| range.len()).sum();
+        assert_eq!(covered, tree.len());
+    }
 
-    write!(
-        page.content,
-        r#"{}<summary class="{}"><a href="/note/{}">{}</a></summary>"#,
-        details.as_str(),
-        summary_class,
-        node.id,
-        html_escape::encode_text(title)
-    )
-    .unwrap();
-}
-
-fn render_context_node(page: &mut TreePage, node: &NoteTreeNode, current_note_id: Option<i32>) {
-    let class_str = "note-item opacity-50";
-    write!(
-        page.content,
-        r#"<li class="{}" draggable="true" data-note-id="{}">"#,
-        class_str, node.id
-    )
-    .unwrap();
-
-    let details = build_details(node.id, current_note_id, node);
-
-    write!(
-        page.content,
-        r#"{}<summary><a href="/note/{}">{}</a></summary>"#,
-        details,
-        node.id,
-        html_escape::encode_text(node.title.as_deref().unwrap_or("Untitled"))
-    )
-    .unwrap();
+    #[test]
+    fn empty_trees_have_no_pages() {
+        assert!(paginate_tree(&[], &TreeRenderContext::new(None), 10).is_empty());
+    }
 }
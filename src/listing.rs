@@ -0,0 +1,120 @@
+use draftsmith_rest_api::client::notes::NoteWithoutFts;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::fmt::Display;
+use std::str::FromStr;
+
+pub const DEFAULT_PER_PAGE: usize = 50;
+pub const MAX_PER_PAGE: usize = 500;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortField {
+    Title,
+    Created,
+    #[default]
+    Modified,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    Asc,
+    #[default]
+    Desc,
+}
+
+/// For query fields filled from a `<select>` whose "any" option is
+/// empty, e.g. `?tag=`, which should mean no filter rather than a 400
+pub fn empty_as_none<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: Display,
+{
+    match Option::<String>::deserialize(deserializer)? {
+        Some(value) if !value.trim().is_empty() => value
+            .trim()
+            .parse()
+            .map(Some)
+            .map_err(serde::de::Error::custom),
+        _ => Ok(None),
+    }
+}
+
+/// Query parameters for paginated note lists.
+///
+/// `page` is already taken by the sidebar tree, so the list page is `p`.
+#[derive(Debug, Default, Deserialize)]
+pub struct ListParams {
+    pub p: Option<usize>,
+    pub per_page: Option<usize>,
+    pub sort: Option<SortField>,
+    pub order: Option<SortOrder>,
+}
+
+/// One page of a list, along with what the template needs to draw the pager
+#[derive(Debug, Serialize)]
+pub struct ListPage<T> {
+    pub items: Vec<T>,
+    pub page: usize,
+    pub per_page: usize,
+    pub total_pages: usize,
+    pub total_items: usize,
+    pub sort: SortField,
+    pub order: SortOrder,
+}
+
+impl ListParams {
+    pub fn sort(&self) -> SortField {
+        self.sort.unwrap_or_default()
+    }
+
+    pub fn order(&self) -> SortOrder {
+        self.order.unwrap_or_default()
+    }
+
+    pub fn per_page(&self) -> usize {
+        self.per_page
+            .unwrap_or(DEFAULT_PER_PAGE)
+            .clamp(1, MAX_PER_PAGE)
+    }
+
+    /// Split `items` into pages and keep the requested one (1-indexed).
+    /// Out of range pages are clamped to the last page.
+    pub fn paginate<T>(&self, items: Vec<T>) -> ListPage<T> {
+        let per_page = self.per_page();
+        let total_items = items.len();
+        let total_pages = total_items.div_ceil(per_page).max(1);
+        let page = self.p.unwrap_or(1).clamp(1, total_pages);
+
+        let items = items
+            .into_iter()
+            .skip((page - 1) * per_page)
+            .take(per_page)
+            .collect();
+
+        ListPage {
+            items,
+            page,
+            per_page,
+            total_pages,
+            total_items,
+            sort: self.sort(),
+            order: self.order(),
+        }
+    }
+}
+
+pub fn sort_notes(notes: &mut [NoteWithoutFts], field: SortField, order: SortOrder) {
+    notes.sort_by(|a, b| {
+        let ordering = match field {
+            SortField::Title => a.title.to_lowercase().cmp(&b.title.to_lowercase()),
+            SortField::Created => a.created_at.cmp(&b.created_at),
+            SortField::Modified => a.modified_at.cmp(&b.modified_at),
+        };
+        match order {
+            SortOrder::Asc => ordering,
+            SortOrder::Desc => ordering.reverse(),
+        }
+    });
+}
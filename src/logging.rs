@@ -0,0 +1,51 @@
+use crate::config::{LogConfig, LogFormat};
+use axum::http::{HeaderName, Request, Response};
+use std::time::Duration;
+use tower_http::request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer};
+use tracing::{field, info, info_span, Span};
+use tracing_subscriber::EnvFilter;
+
+pub const REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+/// Install the global subscriber, the level was checked by `Config::validate`
+pub fn init(config: &LogConfig) {
+    let filter = EnvFilter::try_new(&config.level).unwrap_or_else(|_| EnvFilter::new("info"));
+    let builder = tracing_subscriber::fmt().with_env_filter(filter);
+    match config.format {
+        LogFormat::Pretty => builder.pretty().init(),
+        LogFormat::Json => builder.json().with_current_span(true).init(),
+    }
+}
+
+/// Give each request an id, reusing one set by a proxy, and echo it back
+pub fn set_request_id_layer() -> SetRequestIdLayer<MakeRequestUuid> {
+    SetRequestIdLayer::new(REQUEST_ID_HEADER, MakeRequestUuid)
+}
+
+pub fn propagate_request_id_layer() -> PropagateRequestIdLayer {
+    PropagateRequestIdLayer::new(REQUEST_ID_HEADER)
+}
+
+/// A span per request with method, path and request id,
+/// status and latency are filled in by `record_response`
+pub fn request_span<B>(request: &Request<B>) -> Span {
+    let request_id = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|id| id.to_str().ok())
+        .unwrap_or("-");
+    info_span!(
+        "request",
+        request_id = %request_id,
+        method = %request.method(),
+        path = %request.uri().path(),
+        status = field::Empty,
+        latency_ms = field::Empty,
+    )
+}
+
+pub fn record_response<B>(response: &Response<B>, latency: Duration, span: &Span) {
+    span.record("status", response.status().as_u16());
+    span.record("latency_ms", latency.as_millis() as u64);
+    info!("finished request");
+}
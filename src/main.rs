@@ -1,13 +1,30 @@
 use clap::Parser;
+use config::{Config, ServeArgs};
+use tracing::info;
+mod auth;
+pub mod config;
+pub mod datetime;
+pub mod error;
 pub mod flash;
+pub mod history;
 pub mod html_builder;
+pub mod listing;
+pub mod logging;
+pub mod metrics;
+pub mod prefs;
 pub mod server;
+pub mod snapshot;
 pub mod state;
+pub mod store;
+pub mod urls;
 // TODO this should be a module of server
 mod routes;
 mod static_files;
+mod tag_query;
 mod template_context;
 mod templates;
+mod tls;
+mod upstream;
 
 pub const MAX_ITEMS_PER_PAGE: usize = 2000;
 
@@ -16,45 +33,34 @@ pub const MAX_ITEMS_PER_PAGE: usize = 2000;
 #[command(version, about, long_about = None)]
 enum Command {
     /// Serve the Web Application
-    Serve {
-        /// Host for the API server (default: "localhost")
-        #[arg(short = 'S', long, default_value_t = String::from("localhost"), required = false)]
-        api_host: String,
-
-        /// Port for the API server (default: 8080)
-        #[arg(short = 'P', long, default_value_t = 37240, required = false)]
-        api_port: u16,
-
-        /// Scheme for the API (http or https) (default: "http")
-        #[arg(short = 't', long, default_value_t = String::from("http"), required = false)]
-        api_scheme: String,
-
-        /// Port for the Web App
-        #[arg(short = 'p', long, default_value_t = String::from("8080"), required = false)]
-        port: String,
-
-        /// Host for the Web App
-        #[arg(short = 's', long, default_value_t = String::from("0.0.0.0"), required = false)]
-        host: String,
-    },
+    Serve(ServeArgs),
 }
 
 fn main() {
     let command = Command::parse();
 
     match &command {
-        Command::Serve {
-            api_host,
-            api_port,
-            api_scheme,
-            host,
-            port,
-        } => {
-            // Placeholder for serving logic
-            println!(
-                "Serving Web App at {host}:{port} using API {api_scheme}://{api_host}:{api_port}"
+        Command::Serve(args) => {
+            let config = Config::load(args).unwrap_or_else(|e| {
+                // Logging isn't set up until the config is known
+                eprintln!("Invalid configuration: {}", e);
+                std::process::exit(2);
+            });
+
+            if args.print_config {
+                print!("{}", config.to_printable_toml());
+                return;
+            }
+
+            logging::init(&config.log);
+            info!(
+                addr = %config.bind_addr(),
+                api = %config.api_addr(),
+                base_path = %config.server.base_path,
+                tls = config.tls.files().is_some(),
+                "Serving Web App"
             );
-            server::serve(api_scheme, api_host, api_port, host, port);
+            server::serve(config);
         }
     }
 }
@@ -0,0 +1,164 @@
+//! Prometheus metrics, served at `/metrics`.
+//!
+//! Everything is recorded through the `metrics` facade, so call sites only
+//! need the small helpers below and never touch the exporter directly.
+
+use axum::{
+    extract::{MatchedPath, Request, State},
+    http::{header::CONTENT_TYPE, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use metrics::{counter, gauge, histogram};
+use metrics_exporter_prometheus::{Matcher, PrometheusBuilder, PrometheusHandle};
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tower_sessions::{
+    session::{Id, Record},
+    session_store, SessionStore,
+};
+
+/// Seconds, from a fast template render to a slow upstream call
+const LATENCY_BUCKETS: &[f64] = &[
+    0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// Install the global recorder, the handle renders the scrape output
+pub fn install() -> PrometheusHandle {
+    PrometheusBuilder::new()
+        .set_buckets_for_metric(Matcher::Suffix(String::from("_seconds")), LATENCY_BUCKETS)
+        .and_then(|builder| builder.install_recorder())
+        .unwrap_or_else(|e| panic!("Unable to install metrics recorder. Error: {:#}", e))
+}
+
+pub async fn route_metrics(State(handle): State<PrometheusHandle>) -> Response {
+    (
+        StatusCode::OK,
+        [(CONTENT_TYPE, "text/plain; version=0.0.4")],
+        handle.render(),
+    )
+        .into_response()
+}
+
+/// Count requests and time them per route pattern, e.g. "/note/:id",
+/// so note ids don't each become their own series.
+///
+/// Added with `route_layer`, so it only sees requests that matched a route.
+pub async fn track_requests(request: Request, next: Next) -> Response {
+    let Some(route) = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+    else {
+        return next.run(request).await;
+    };
+    let method = request.method().to_string();
+
+    let started = Instant::now();
+    let response = next.run(request).await;
+    let elapsed = started.elapsed();
+
+    let status = response.status().as_u16().to_string();
+    counter!("http_requests_total", "method" => method.clone(), "route" => route.clone(), "status" => status)
+        .increment(1);
+    histogram!("http_request_duration_seconds", "method" => method, "route" => route)
+        .record(elapsed.as_secs_f64());
+
+    response
+}
+
+/// Recorded by `upstream::call` for every Draftsmith API client call
+pub fn record_upstream_call(call: &'static str, elapsed: Duration, failed: bool) {
+    counter!("upstream_requests_total", "call" => call).increment(1);
+    if failed {
+        counter!("upstream_errors_total", "call" => call).increment(1);
+    }
+    histogram!("upstream_request_duration_seconds", "call" => call).record(elapsed.as_secs_f64());
+}
+
+pub fn record_template_render(template: &str, elapsed: Duration) {
+    histogram!("template_render_duration_seconds", "template" => template.to_string())
+        .record(elapsed.as_secs_f64());
+}
+
+pub fn record_asset_bytes(bytes: usize) {
+    counter!("asset_proxy_bytes_total").increment(bytes as u64);
+}
+
+/// A flash message was stored where none was waiting.
+///
+/// Counters rather than a pending gauge: messages in sessions that expire
+/// unread are never taken, so a gauge would only ever drift upwards.
+pub fn flash_queued() {
+    counter!("flash_messages_queued_total").increment(1);
+}
+
+/// A waiting flash message was shown
+pub fn flash_taken() {
+    counter!("flash_messages_shown_total").increment(1);
+}
+
+/// Session store wrapper that keeps the `sessions_stored` gauge up to date.
+///
+/// Sessions that expire without being loaded again stay counted
+/// until the inner store drops them, the same as its memory use.
+#[derive(Debug, Clone)]
+pub struct MeteredStore<S> {
+    inner: S,
+    ids: Arc<Mutex<HashSet<Id>>>,
+}
+
+impl<S> MeteredStore<S> {
+    pub fn new(inner: S) -> Self {
+        Self {
+            inner,
+            ids: Arc::new(Mutex::new(HashSet::new())),
+        }
+    }
+
+    fn update(&self, change: impl FnOnce(&mut HashSet<Id>)) {
+        let mut ids = self.ids.lock().unwrap_or_else(|e| e.into_inner());
+        change(&mut ids);
+        gauge!("sessions_stored").set(ids.len() as f64);
+    }
+}
+
+#[axum::async_trait]
+impl<S: SessionStore> SessionStore for MeteredStore<S> {
+    async fn create(&self, record: &mut Record) -> session_store::Result<()> {
+        self.inner.create(record).await?;
+        let id = record.id;
+        self.update(|ids| {
+            ids.insert(id);
+        });
+        Ok(())
+    }
+
+    async fn save(&self, record: &Record) -> session_store::Result<()> {
+        self.inner.save(record).await?;
+        let id = record.id;
+        self.update(|ids| {
+            ids.insert(id);
+        });
+        Ok(())
+    }
+
+    async fn load(&self, session_id: &Id) -> session_store::Result<Option<Record>> {
+        let record = self.inner.load(session_id).await?;
+        if record.is_none() {
+            self.update(|ids| {
+                ids.remove(session_id);
+            });
+        }
+        Ok(record)
+    }
+
+    async fn delete(&self, session_id: &Id) -> session_store::Result<()> {
+        self.inner.delete(session_id).await?;
+        self.update(|ids| {
+            ids.remove(session_id);
+        });
+        Ok(())
+    }
+}
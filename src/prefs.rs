@@ -0,0 +1,67 @@
+use crate::datetime;
+use crate::MAX_ITEMS_PER_PAGE;
+use axum::async_trait;
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use tower_sessions::{session::Error as SessionError, Session};
+
+/// Rendered sidebar items per tree page unless the user picked another size
+pub const DEFAULT_TREE_PAGE_SIZE: usize = 200;
+
+/// The sizes offered in the sidebar, any value up to the maximum is accepted
+pub const TREE_PAGE_SIZES: &[usize] = &[50, 100, 200, 500, 1000];
+
+/// Expanded sidebar notes remembered per user, the oldest are forgotten first
+pub const MAX_EXPANDED_NOTES: usize = 500;
+
+/// Settings each user picks for themselves, kept in their session
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Preferences {
+    pub tree_page_size: Option<usize>,
+    /// Sidebar notes the user expanded, in the order they were opened
+    pub expanded_ids: Vec<i32>,
+    /// IANA timezone name, the server's `timezone` when unset
+    pub timezone: Option<String>,
+}
+
+impl Preferences {
+    pub fn tree_page_size(&self) -> usize {
+        self.tree_page_size
+            .unwrap_or(DEFAULT_TREE_PAGE_SIZE)
+            .clamp(1, MAX_ITEMS_PER_PAGE)
+    }
+
+    /// The user's timezone, or `server_timezone` if they haven't picked one
+    pub fn display_timezone(&self, server_timezone: Tz) -> Tz {
+        datetime::display_timezone(self.timezone.as_deref(), server_timezone)
+    }
+
+    pub fn set_expanded(&mut self, id: i32, open: bool) {
+        self.expanded_ids.retain(|&expanded| expanded != id);
+        if open {
+            self.expanded_ids.push(id);
+            if self.expanded_ids.len() > MAX_EXPANDED_NOTES {
+                let excess = self.expanded_ids.len() - MAX_EXPANDED_NOTES;
+                self.expanded_ids.drain(..excess);
+            }
+        }
+    }
+}
+
+#[async_trait]
+pub trait PreferenceStore {
+    async fn prefs(&self) -> Result<Preferences, SessionError>;
+    async fn set_prefs(&self, prefs: Preferences) -> Result<(), SessionError>;
+}
+
+#[async_trait]
+impl PreferenceStore for Session {
+    async fn prefs(&self) -> Result<Preferences, SessionError> {
+        Ok(self.get::<Preferences>("prefs").await?.unwrap_or_default())
+    }
+
+    async fn set_prefs(&self, prefs: Preferences) -> Result<(), SessionError> {
+        self.insert("prefs", prefs).await
+    }
+}
@@ -1,6 +1,7 @@
 use crate::state::AppState;
 use crate::template_context::{BodyTemplateContext, PaginationParams};
-use crate::templates::{handle_template_error, ENV};
+use crate::error::AppError;
+use crate::templates::render_page;
 use axum::{
     extract::{Path, Query, State},
     response::{Html, Response, IntoResponse},
@@ -10,44 +11,33 @@ use draftsmith_rest_api::client::assets::{list_assets, delete_asset};
 use minijinja::context;
 use tower_sessions::Session;
 use crate::flash::{FlashMessage, FlashMessageStore};
+use crate::urls::url_for;
+use crate::upstream;
 
 pub async fn route_list_assets(
     session: Session,
     State(state): State<AppState>,
     Query(params): Query<PaginationParams>,
-) -> Html<String> {
+) -> Result<Html<String>, AppError> {
     let api_addr: String = state.api_addr.clone();
 
     // Get the body data
-    let body_handler =
-        match BodyTemplateContext::new(session, Query(params), api_addr.clone(), None).await {
-            Ok(handler) => handler,
-            Err(e) => {
-                eprintln!("Failed to create body handler: {:#?}", e);
-                return Html(String::from("<h1>Error getting page data</h1>"));
-            }
-        };
+    let body_handler = BodyTemplateContext::new(session, Query(params), &state, None)
+        .await
+        .map_err(|e| AppError::upstream("Unable to reach the Draftsmith API", e))?;
 
     // Get assets list
-    let assets = match list_assets(&api_addr, None).await {
-        Ok(assets) => assets,
-        Err(e) => {
-            eprintln!("Failed to fetch assets: {:#?}", e);
-            return Html(String::from("<h1>Error fetching assets</h1>"));
-        }
-    };
-
-    let template = ENV.get_template("body/assets.html").unwrap_or_else(|e| {
-        panic!("Failed to load template. Error: {:#}", e);
-    });
+    let assets = upstream::call("list_assets", list_assets(&api_addr, None))
+        .await
+        .map_err(|e| {
+            AppError::upstream("Unable to fetch assets", e).with_layout(body_handler.ctx.clone())
+        })?;
 
     let ctx = context! { ..body_handler.ctx, ..context! {
         assets => assets,
     }};
 
-    let rendered = template.render(ctx).unwrap_or_else(handle_template_error);
-
-    Html(rendered)
+    render_page("body/assets.html", ctx)
 }
 
 pub async fn route_edit_asset(
@@ -62,7 +52,7 @@ pub async fn route_edit_asset(
     // Redirect back to assets list
     Response::builder()
         .status(StatusCode::FOUND)
-        .header("Location", "/assets")
+        .header("Location", url_for("/assets"))
         .body(axum::body::Body::empty())
         .unwrap()
 }
@@ -72,7 +62,7 @@ pub async fn route_delete_asset(
     Path(asset_id): Path<i32>,
     session: Session,
 ) -> impl IntoResponse {
-    match delete_asset(&state.api_addr, asset_id).await {
+    match upstream::call("delete_asset", delete_asset(&state.api_addr, asset_id)).await {
         Ok(()) => {
             let _ = session.set_flash(FlashMessage::success("Asset deleted successfully")).await;
         }
@@ -84,7 +74,7 @@ pub async fn route_delete_asset(
     // Redirect back to assets list
     Response::builder()
         .status(StatusCode::FOUND)
-        .header("Location", "/assets")
+        .header("Location", url_for("/assets"))
         .body(axum::body::Body::empty())
         .unwrap()
 }
@@ -0,0 +1,85 @@
+use crate::state::AppState;
+use crate::upstream;
+use axum::{
+    extract::State,
+    http::{header::CACHE_CONTROL, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use draftsmith_rest_api::client::fetch_note_tree;
+use serde::Serialize;
+use serde_json::json;
+use std::time::{Duration, Instant};
+
+/// How long `/readyz` waits for the API before reporting it unavailable
+const UPSTREAM_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Serialize)]
+pub struct Readiness {
+    ready: bool,
+    version: &'static str,
+    upstream: UpstreamStatus,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UpstreamStatus {
+    url: String,
+    /// Round trip of the probe in milliseconds, if the API answered
+    latency_ms: Option<u128>,
+    error: Option<String>,
+}
+
+/// Liveness: the process is up and serving requests
+pub async fn route_healthz() -> Response {
+    (
+        [(CACHE_CONTROL, "no-store")],
+        Json(json!({ "status": "ok" })),
+    )
+        .into_response()
+}
+
+/// Readiness: the Draftsmith API serves notes.
+///
+/// Fetches the note tree through the API client, which every page needs.
+/// Only a successful answer counts, an error status or a body the client
+/// can't read means the API isn't usable.
+pub async fn route_readyz(State(state): State<AppState>) -> Response {
+    let upstream = probe_upstream(&state.api_addr).await;
+    let ready = upstream.error.is_none();
+    let status = if ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+
+    (
+        status,
+        [(CACHE_CONTROL, "no-store")],
+        Json(Readiness {
+            ready,
+            version: env!("CARGO_PKG_VERSION"),
+            upstream,
+        }),
+    )
+        .into_response()
+}
+
+async fn probe_upstream(api_addr: &str) -> UpstreamStatus {
+    let mut status = UpstreamStatus {
+        url: api_addr.to_string(),
+        latency_ms: None,
+        error: None,
+    };
+
+    let started = Instant::now();
+    let probe = upstream::call("fetch_note_tree", fetch_note_tree(api_addr));
+    match tokio::time::timeout(UPSTREAM_TIMEOUT, probe).await {
+        Ok(Ok(_)) => status.latency_ms = Some(started.elapsed().as_millis()),
+        Ok(Err(e)) => status.error = Some(format!("Unable to fetch the note tree: {}", e)),
+        Err(_) => {
+            status.error = Some(format!("No answer within {}s", UPSTREAM_TIMEOUT.as_secs()))
+        }
+    }
+
+    status
+}
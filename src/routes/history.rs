@@ -0,0 +1,85 @@
+use crate::error::AppError;
+use crate::flash::{FlashMessage, FlashMessageStore};
+use crate::history::{self, ViewHistory};
+use crate::state::AppState;
+use crate::template_context::{BodyTemplateContext, PaginationParams};
+use crate::templates::render_page;
+use crate::urls::redirect;
+use axum::{
+    extract::{Query, State},
+    response::{Html, Redirect},
+};
+use minijinja::context;
+use serde::Deserialize;
+use tower_sessions::Session;
+use tracing::error;
+
+#[derive(Debug, Deserialize)]
+pub struct StepParams {
+    /// The note the user is on, if any
+    pub from: Option<i32>,
+}
+
+/// The notes this session opened, most recent first
+pub async fn route_history(
+    session: Session,
+    State(state): State<AppState>,
+    Query(params): Query<PaginationParams>,
+) -> Result<Html<String>, AppError> {
+    let body_handler = BodyTemplateContext::new(session.clone(), Query(params), &state, None)
+        .await
+        .map_err(|e| AppError::upstream("Unable to reach the Draftsmith API", e))?;
+
+    let mut entries = session.history().await.unwrap_or_else(|e| {
+        error!(error = ?e, "Failed to read view history");
+        Vec::new()
+    });
+    entries.reverse();
+
+    let ctx = context! { ..body_handler.ctx, ..context! {
+        entries => entries,
+    }};
+
+    render_page("body/history.html", ctx)
+}
+
+async fn step(session: Session, from: Option<i32>, forward: bool) -> Redirect {
+    let entries = session.history().await.unwrap_or_default();
+
+    match history::step(&entries, from, forward) {
+        Some(id) => redirect(&format!("/note/{id}?history=true")),
+        None => {
+            let message = if forward {
+                "This is the latest note in your history"
+            } else {
+                "This is the earliest note in your history"
+            };
+            session
+                .set_flash(FlashMessage::info(message))
+                .await
+                .unwrap();
+            match from {
+                Some(id) => redirect(&format!("/note/{id}?history=true")),
+                None => redirect("/history"),
+            }
+        }
+    }
+}
+
+pub async fn route_history_back(session: Session, Query(params): Query<StepParams>) -> Redirect {
+    step(session, params.from, false).await
+}
+
+pub async fn route_history_forward(session: Session, Query(params): Query<StepParams>) -> Redirect {
+    step(session, params.from, true).await
+}
+
+pub async fn route_clear_history(session: Session) -> Redirect {
+    let message = match session.clear_history().await {
+        Ok(()) => FlashMessage::success("History cleared"),
+        Err(e) => FlashMessage::error(format!("Failed to clear history: {}", e)),
+    };
+    session.set_flash(message).await.unwrap();
+
+    redirect("/history")
+}
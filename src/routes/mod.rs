@@ -1,5 +1,10 @@
+pub mod note_index;
 pub mod notes;
+pub mod prefs;
 pub mod recent;
 pub mod search;
+pub mod tree;
 pub mod tags;
 pub mod assets;
+pub mod health;
+pub mod history;
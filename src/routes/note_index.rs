@@ -0,0 +1,133 @@
+use crate::state::AppState;
+use axum::{
+    extract::State,
+    http::{
+        header::{CACHE_CONTROL, CONTENT_TYPE, ETAG, IF_NONE_MATCH},
+        HeaderMap, StatusCode,
+    },
+    response::{IntoResponse, Response},
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use draftsmith_rest_api::client::tags::{get_tag_tree, TagTreeNode};
+use draftsmith_rest_api::client::{fetch_note_tree, NoteTreeNode};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use crate::upstream;
+use tracing::error;
+
+/// A single entry in the quick switcher index
+#[derive(Debug, Serialize)]
+pub struct IndexEntry {
+    pub id: i32,
+    pub title: String,
+    /// Titles of all ancestors and the entry itself, joined with " / "
+    pub path: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct NoteIndex {
+    notes: Vec<IndexEntry>,
+    tags: Vec<IndexEntry>,
+}
+
+fn flatten_note_tree(nodes: &[NoteTreeNode], prefix: &str, out: &mut Vec<IndexEntry>) {
+    for node in nodes {
+        let title = node.title.clone().unwrap_or_else(|| String::from("Untitled"));
+        let path = if prefix.is_empty() {
+            title.clone()
+        } else {
+            format!("{} / {}", prefix, title)
+        };
+        flatten_note_tree(&node.children, &path, out);
+        out.push(IndexEntry {
+            id: node.id,
+            title,
+            path,
+        });
+    }
+}
+
+pub(crate) fn flatten_tag_tree(nodes: &[TagTreeNode], prefix: &str, out: &mut Vec<IndexEntry>) {
+    for node in nodes {
+        let path = if prefix.is_empty() {
+            node.name.clone()
+        } else {
+            format!("{} / {}", prefix, node.name)
+        };
+        flatten_tag_tree(&node.children, &path, out);
+        out.push(IndexEntry {
+            id: node.id,
+            title: node.name.clone(),
+            path,
+        });
+    }
+}
+
+/// Serve a JSON index of every note and tag for the command palette.
+///
+/// The ETag is a hash of the body, so clients revalidating with
+/// `If-None-Match` only download the index again when the tree changed.
+pub async fn route_note_index(State(state): State<AppState>, headers: HeaderMap) -> Response {
+    let api_addr: String = state.api_addr.clone();
+
+    let note_tree = match upstream::call("fetch_note_tree", fetch_note_tree(&api_addr)).await {
+        Ok(tree) => tree,
+        Err(e) => {
+            error!(error = ?e, "Failed to get note tree");
+            return (
+                StatusCode::BAD_GATEWAY,
+                [(CACHE_CONTROL, "no-store")],
+                axum::Json(serde_json::json!({ "error": "Unable to fetch note tree" })),
+            )
+                .into_response();
+        }
+    };
+
+    // A missing tag tree shouldn't stop the palette from finding notes
+    let tag_tree = upstream::call("get_tag_tree", get_tag_tree(&api_addr)).await.unwrap_or_else(|e| {
+        error!(error = ?e, "Failed to get tag tree");
+        Vec::new()
+    });
+
+    let mut index = NoteIndex {
+        notes: Vec::new(),
+        tags: Vec::new(),
+    };
+    flatten_note_tree(&note_tree, "", &mut index.notes);
+    flatten_tag_tree(&tag_tree, "", &mut index.tags);
+
+    let body = match serde_json::to_vec(&index) {
+        Ok(body) => body,
+        Err(e) => {
+            error!(error = ?e, "Failed to serialize note index");
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+
+    let etag = format!("\"{}\"", URL_SAFE_NO_PAD.encode(Sha256::digest(&body)));
+
+    let matches_etag = headers
+        .get(IF_NONE_MATCH)
+        .and_then(|h| h.to_str().ok())
+        .map(|value| value.split(',').any(|tag| tag.trim() == etag))
+        .unwrap_or(false);
+
+    if matches_etag {
+        return (
+            StatusCode::NOT_MODIFIED,
+            [(ETAG, etag), (CACHE_CONTROL, String::from("no-cache"))],
+        )
+            .into_response();
+    }
+
+    (
+        StatusCode::OK,
+        [
+            (CONTENT_TYPE, String::from("application/json")),
+            (ETAG, etag),
+            (CACHE_CONTROL, String::from("no-cache")),
+        ],
+        body,
+    )
+        .into_response()
+}
@@ -0,0 +1,158 @@
+use axum::{extract::State, response::Redirect};
+use axum_extra::extract::Form;
+use crate::flash::{FlashMessage, FlashMessageStore};
+use crate::routes::notes::note_move::move_note;
+use crate::state::AppState;
+use crate::urls;
+use draftsmith_rest_api::client::delete_note;
+use draftsmith_rest_api::client::tags::{attach_tag_to_note, detach_tag_from_note, list_note_tags};
+use serde::Deserialize;
+use std::collections::HashSet;
+use tower_sessions::Session;
+use crate::upstream;
+
+#[derive(Debug, Deserialize)]
+pub struct BulkNotesForm {
+    /// Selected notes, repeated as `note_ids=1&note_ids=2`
+    #[serde(default)]
+    note_ids: Vec<i32>,
+    /// "add_tags", "remove_tags", "move" or "delete"
+    action: String,
+    #[serde(default)]
+    tag_ids: Vec<i32>,
+    /// Empty string from the form when no parent was entered
+    parent_id: Option<String>,
+    /// The page to return to
+    redirect_to: Option<String>,
+}
+
+pub async fn route_bulk_notes(
+    session: Session,
+    State(state): State<AppState>,
+    Form(form): Form<BulkNotesForm>,
+) -> Redirect {
+    let api_addr = &state.api_addr;
+    let redirect = urls::safe_redirect(form.redirect_to.as_deref(), "/recent");
+
+    if form.note_ids.is_empty() {
+        session
+            .set_flash(FlashMessage::warning("No notes selected"))
+            .await
+            .unwrap();
+        return redirect;
+    }
+
+    let mut succeeded = 0;
+    let mut errors = Vec::new();
+
+    let verb = match form.action.as_str() {
+        "add_tags" | "remove_tags" => {
+            if form.tag_ids.is_empty() {
+                session
+                    .set_flash(FlashMessage::warning("No tags selected"))
+                    .await
+                    .unwrap();
+                return redirect;
+            }
+
+            // Skip pairs that are already in the requested state
+            let existing: HashSet<(i32, i32)> = match upstream::call("list_note_tags", list_note_tags(api_addr)).await {
+                Ok(note_tags) => note_tags
+                    .into_iter()
+                    .map(|nt| (nt.note_id, nt.tag_id))
+                    .collect(),
+                Err(e) => {
+                    session
+                        .set_flash(FlashMessage::error(format!("Failed to get note tags: {}", e)))
+                        .await
+                        .unwrap();
+                    return redirect;
+                }
+            };
+
+            let attach = form.action == "add_tags";
+            for &note_id in &form.note_ids {
+                let mut note_ok = true;
+                for &tag_id in &form.tag_ids {
+                    if existing.contains(&(note_id, tag_id)) == attach {
+                        continue;
+                    }
+                    let result = if attach {
+                        upstream::call("attach_tag_to_note", attach_tag_to_note(api_addr, note_id, tag_id)).await.map(|_| ())
+                    } else {
+                        upstream::call("detach_tag_from_note", detach_tag_from_note(api_addr, note_id, tag_id)).await
+                    };
+                    if let Err(e) = result {
+                        note_ok = false;
+                        errors.push(format!("#{}: {}", note_id, e));
+                    }
+                }
+                if note_ok {
+                    succeeded += 1;
+                }
+            }
+
+            if attach {
+                "Tagged"
+            } else {
+                "Untagged"
+            }
+        }
+        "move" => {
+            // An empty parent moves the notes to the top level
+            let parent_id = match form.parent_id.as_deref().map(str::trim) {
+                None | Some("") => 0,
+                Some(id) => match id.parse::<i32>() {
+                    Ok(id) => id,
+                    Err(_) => {
+                        session
+                            .set_flash(FlashMessage::error(format!("Invalid parent id: {}", id)))
+                            .await
+                            .unwrap();
+                        return redirect;
+                    }
+                },
+            };
+
+            for &note_id in &form.note_ids {
+                if note_id == parent_id {
+                    errors.push(format!("#{}: a note can't be its own parent", note_id));
+                    continue;
+                }
+                match move_note(api_addr, note_id, parent_id).await {
+                    Ok(_) => succeeded += 1,
+                    Err(e) => errors.push(format!("#{}: {}", note_id, e)),
+                }
+            }
+
+            "Moved"
+        }
+        "delete" => {
+            for &note_id in &form.note_ids {
+                match upstream::call("delete_note", delete_note(api_addr, note_id)).await {
+                    Ok(_) => succeeded += 1,
+                    Err(e) => errors.push(format!("#{}: {}", note_id, e)),
+                }
+            }
+
+            "Deleted"
+        }
+        _ => {
+            session
+                .set_flash(FlashMessage::error("Invalid action specified"))
+                .await
+                .unwrap();
+            return redirect;
+        }
+    };
+
+    let summary = format!("{} {} of {} note(s)", verb, succeeded, form.note_ids.len());
+    let flash = if errors.is_empty() {
+        FlashMessage::success(summary)
+    } else {
+        FlashMessage::warning(format!("{}. Problems: {}", summary, errors.join("; ")))
+    };
+    session.set_flash(flash).await.unwrap();
+
+    redirect
+}
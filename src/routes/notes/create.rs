@@ -1,6 +1,6 @@
 use axum::{
     extract::{Path, Query, State},
-    response::{IntoResponse, Response, Redirect},
+    response::{IntoResponse, Response},
 };
 use crate::flash::{FlashMessage, FlashMessageStore};
 use crate::state::AppState;
@@ -9,6 +9,9 @@ use draftsmith_rest_api::client::{
     fetch_note, CreateNoteRequest, get_note_breadcrumbs,
 };
 use tower_sessions::Session;
+use crate::urls::redirect;
+use crate::upstream;
+use tracing::error;
 
 #[derive(Debug, Default, serde::Deserialize)]
 pub struct CreateNoteParams {
@@ -23,7 +26,7 @@ async fn handle_attachment(
     as_sibling: bool,
 ) -> Result<String, String> {
     if as_sibling {
-        let breadcrumbs = get_note_breadcrumbs(api_addr, reference_id)
+        let breadcrumbs = upstream::call("get_note_breadcrumbs", get_note_breadcrumbs(api_addr, reference_id))
             .await
             .map_err(|e| format!("Failed to get breadcrumbs: {}", e))?;
 
@@ -49,7 +52,7 @@ async fn attach_note(api_addr: &str, child_id: i32, parent_id: i32) -> Result<()
         parent_note_id: Some(parent_id),
     };
 
-    attach_child_note(api_addr, attach_request)
+    upstream::call("attach_child_note", attach_child_note(api_addr, attach_request))
         .await
         .map_err(|e| format!("Failed to attach note: {}", e))
 }
@@ -65,7 +68,7 @@ pub async fn route_create(
         content: String::new(),
     };
 
-    match create_note(&state.api_addr, create_request).await {
+    match upstream::call("create_note", create_note(&state.api_addr, create_request)).await {
         Ok(note) => {
             let mut message = format!("Note created successfully #{}", note.id);
 
@@ -79,17 +82,17 @@ pub async fn route_create(
             session
                 .set_flash(FlashMessage::success(message))
                 .await
-                .unwrap_or_else(|e| eprintln!("Failed to set flash message: {}", e));
+                .unwrap_or_else(|e| error!(error = %e, "Failed to set flash message"));
 
-            Redirect::to(&format!("/edit/{}", note.id)).into_response()
+            redirect(&format!("/edit/{}", note.id)).into_response()
         }
         Err(e) => {
             session
                 .set_flash(FlashMessage::error(format!("Failed to create note: {}", e)))
                 .await
-                .unwrap_or_else(|e| eprintln!("Failed to set flash message: {}", e));
+                .unwrap_or_else(|e| error!(error = %e, "Failed to set flash message"));
 
-            Redirect::to("/").into_response()
+            redirect("/").into_response()
         }
     }
 }
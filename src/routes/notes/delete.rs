@@ -1,11 +1,13 @@
 use axum::{
     extract::{Path, State},
-    response::{IntoResponse, Response, Redirect},
+    response::{IntoResponse, Response},
 };
 use crate::flash::{FlashMessage, FlashMessageStore};
 use crate::state::AppState;
 use draftsmith_rest_api::client::delete_note;
 use tower_sessions::Session;
+use crate::urls::redirect;
+use crate::upstream;
 
 pub async fn route_delete(
     session: Session,
@@ -14,14 +16,14 @@ pub async fn route_delete(
 ) -> Response {
     let api_addr: String = state.api_addr.clone();
 
-    match delete_note(&api_addr, id).await {
+    match upstream::call("delete_note", delete_note(&api_addr, id)).await {
         Ok(_) => {
             session
                 .set_flash(FlashMessage::success("Note deleted successfully"))
                 .await
                 .unwrap();
             
-            Redirect::to("/").into_response()
+            redirect("/").into_response()
         }
         Err(e) => {
             session
@@ -29,7 +31,7 @@ pub async fn route_delete(
                 .await
                 .unwrap();
             
-            Redirect::to(&format!("/note/{id}")).into_response()
+            redirect(&format!("/note/{id}")).into_response()
         }
     }
 }
@@ -1,48 +1,45 @@
 use crate::template_context::{NoteTemplateContext, PaginationParams};
 use axum::{
     extract::{Path, Query},
+    http::StatusCode,
     response::{Html, Redirect},
     Form,
 };
 
 use crate::flash::{FlashMessage, FlashMessageStore};
 use crate::state::AppState;
-use crate::templates::{handle_not_found, handle_template_error, ENV};
-use axum::{
-    extract::State,
-    response::{IntoResponse, Response},
-};
+use crate::error::AppError;
+use crate::templates::render_page;
+use axum::extract::State;
 use draftsmith_rest_api::client::{update_note, UpdateNoteRequest};
 use tower_sessions::Session;
+use crate::urls::redirect;
+use crate::upstream;
 
 pub async fn route_edit(
     session: Session,
     State(state): State<AppState>,
     Path(id): Path<i32>,
     Query(params): Query<PaginationParams>,
-) -> Response {
-    let api_addr: String = state.api_addr.clone();
+) -> Result<Html<String>, AppError> {
     // Get note data
-    let note_handler =
-        match NoteTemplateContext::new(session.clone(), Query(params), api_addr, id).await {
-            Ok(data) => data,
-            Err(e) => {
-                eprintln!("Failed to get note data: {:#}", e);
-                return handle_not_found(session).await.into_response();
-            }
-        };
+    let note_handler = NoteTemplateContext::new(session, Query(params), &state, id)
+        .await
+        .map_err(|e| {
+            AppError::upstream(format!("Unable to load note #{}", id), e)
+                .or_not_found(format!("Note #{} does not exist", id))
+        })?;
 
-    // Load template
-    let template = ENV
-        .get_template("body/note/edit.html")
-        .unwrap_or_else(|e| panic!("Failed to load template. Error: {:#}", e));
-
-    // Render the template
-    let rendered = template
-        .render(note_handler.ctx)
-        .unwrap_or_else(handle_template_error);
+    // Saving would fail anyway, don't let the user type into a stale copy
+    if note_handler.read_only {
+        return Err(AppError::new(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "The Draftsmith API is unreachable, notes can't be edited until it's back",
+        )
+        .with_layout(note_handler.ctx));
+    }
 
-    Html(rendered).into_response()
+    render_page("body/note/edit.html", note_handler.ctx)
 }
 
 pub async fn route_update_note(
@@ -55,7 +52,7 @@ pub async fn route_update_note(
 
     let api_addr: String = state.api_addr.clone();
 
-    match update_note(&api_addr, id, note).await {
+    match upstream::call("update_note", update_note(&api_addr, id, note)).await {
         Ok(_) => {
             session
                 .set_flash(FlashMessage::success("Note updated successfully"))
@@ -70,5 +67,5 @@ pub async fn route_update_note(
         }
     }
 
-    Redirect::to(&format!("/note/{id}"))
+    redirect(&format!("/note/{id}"))
 }
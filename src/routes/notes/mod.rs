@@ -4,3 +4,5 @@ pub mod view;
 pub mod delete;
 pub mod create;
 pub mod tags;
+pub mod bulk;
+pub mod pin;
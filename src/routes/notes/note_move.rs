@@ -1,4 +1,5 @@
-use crate::templates::{handle_template_error, ENV};
+use crate::error::AppError;
+use crate::templates::render_page;
 use serde::Deserialize;
 use crate::template_context::{NoteTemplateContext, PaginationParams};
 
@@ -9,42 +10,30 @@ use draftsmith_rest_api::client::{
     AttachChildRequest, NoteBreadcrumb,
 };
 use tower_sessions::Session;
-use crate::templates::handle_not_found;
 use axum::{
     extract::{Path, Query, State},
-    response::{Html, IntoResponse, Response, Redirect},
+    response::{Html, Redirect},
     Form,
 };
+use crate::urls::redirect;
+use crate::upstream;
+use tracing::error;
 
 pub async fn route_move_note_get(
     session: Session,
     State(state): State<AppState>,
     Path(note_id): Path<i32>,
     Query(params): Query<PaginationParams>,
-) -> Response {
-    let api_addr: String = state.api_addr.clone();
-
+) -> Result<Html<String>, AppError> {
     // Get note data
-    let note_handler =
-        match NoteTemplateContext::new(session.clone(), Query(params), api_addr, note_id).await {
-            Ok(data) => data,
-            Err(e) => {
-                eprintln!("Failed to get note data: {:#}", e);
-                return handle_not_found(session).await.into_response();
-            }
-        };
-
-
-    // Load and render template
-    let template = ENV.get_template("body/note/move.html").unwrap_or_else(|e| {
-        panic!("Failed to load template. Error: {:#}", e);
-    });
-
-    let rendered = template
-        .render(note_handler.ctx)
-        .unwrap_or_else(handle_template_error);
-
-    Html(rendered).into_response()
+    let note_handler = NoteTemplateContext::new(session, Query(params), &state, note_id)
+        .await
+        .map_err(|e| {
+            AppError::upstream(format!("Unable to load note #{}", note_id), e)
+                .or_not_found(format!("Note #{} does not exist", note_id))
+        })?;
+
+    render_page("body/note/move.html", note_handler.ctx)
 }
 
 pub async fn route_detach_note_post(
@@ -54,7 +43,7 @@ pub async fn route_detach_note_post(
 ) -> Redirect {
     let api_addr: String = state.api_addr.clone();
 
-    match detach_child_note(&api_addr, note_id).await {
+    match upstream::call("detach_child_note", detach_child_note(&api_addr, note_id)).await {
         Ok(_) => {
             session
                 .set_flash(FlashMessage::success("Note detached successfully"))
@@ -69,23 +58,17 @@ pub async fn route_detach_note_post(
         }
     }
 
-    Redirect::to(&format!("/note/{note_id}"))
+    redirect(&format!("/note/{note_id}"))
 }
 
-pub async fn route_move_note_post(
-    session: Session,
-    State(state): State<AppState>,
-    Path(note_id): Path<i32>,
-    Form(form): Form<MoveNoteForm>,
-) -> Redirect {
-    let api_addr: String = state.api_addr.clone();
-
+/// Detach a note from its current parent (if any) and attach it to `new_parent_id`
+pub async fn move_note(api_addr: &str, note_id: i32, new_parent_id: i32) -> Result<(), String> {
     // Get the breadcrumbs to check for parents
     let breadcrumbs: Option<Vec<NoteBreadcrumb>> =
-        match get_note_breadcrumbs(&api_addr, note_id).await {
+        match upstream::call("get_note_breadcrumbs", get_note_breadcrumbs(api_addr, note_id)).await {
             Ok(b) => Some(b),
             Err(e) => {
-                eprintln!("Failed to get Note Breadcrumbs: {:#?}", e);
+                error!(error = ?e, "Failed to get Note Breadcrumbs");
                 None
             }
         };
@@ -93,18 +76,9 @@ pub async fn route_move_note_post(
     // Detach the note from its current parent if it has one
     if let Some(bc) = breadcrumbs {
         if bc.len() > 1 {
-            match detach_child_note(&api_addr, note_id).await {
-                Ok(_) => (),
-                Err(e) => {
-                    session
-                        .set_flash(FlashMessage::error(format!("Failed to detach note: {}", e)))
-                        .await
-                        .unwrap_or_else(|e| {
-                            eprintln!("Failed to set flash message: {:#?}", e);
-                        });
-                    return Redirect::to(&format!("/note/{note_id}"));
-                }
-            }
+            upstream::call("detach_child_note", detach_child_note(api_addr, note_id))
+                .await
+                .map_err(|e| format!("Failed to detach note: {}", e))?;
         }
     }
 
@@ -113,12 +87,23 @@ pub async fn route_move_note_post(
     // Probably a candidate for a refactor
     // Then attach it to the new parent
     let attach_request = AttachChildRequest {
-        parent_note_id: Some(form.new_parent_id),
+        parent_note_id: Some(new_parent_id),
         child_note_id: note_id,
     };
 
+    upstream::call("attach_child_note", attach_child_note(api_addr, attach_request))
+        .await
+        .map_err(|e| format!("Failed to move note: {}", e))
+}
+
+pub async fn route_move_note_post(
+    session: Session,
+    State(state): State<AppState>,
+    Path(note_id): Path<i32>,
+    Form(form): Form<MoveNoteForm>,
+) -> Redirect {
     // Flash the result
-    match attach_child_note(&api_addr, attach_request).await {
+    match move_note(&state.api_addr, note_id, form.new_parent_id).await {
         Ok(_) => {
             session
                 .set_flash(FlashMessage::success("Note moved successfully"))
@@ -127,13 +112,13 @@ pub async fn route_move_note_post(
         }
         Err(e) => {
             session
-                .set_flash(FlashMessage::error(format!("Failed to move note: {}", e)))
+                .set_flash(FlashMessage::error(e))
                 .await
                 .unwrap();
         }
     }
 
-    Redirect::to(&format!("/note/{note_id}"))
+    redirect(&format!("/note/{note_id}"))
 }
 
 #[derive(Deserialize)]
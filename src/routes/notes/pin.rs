@@ -0,0 +1,88 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Redirect, Response},
+    Json,
+};
+use crate::flash::{FlashMessage, FlashMessageStore};
+use crate::state::AppState;
+use serde::Deserialize;
+use tower_sessions::Session;
+use crate::urls::redirect;
+use tracing::error;
+
+/// Pinned note ids in the order they're shown in the sidebar.
+/// The API has no field for pins, so the web app keeps them itself.
+pub type Pins = Vec<i32>;
+
+#[derive(Debug, Deserialize)]
+pub struct PinOrder {
+    pub ids: Vec<i32>,
+}
+
+async fn set_pinned(session: Session, state: AppState, id: i32, pinned: bool) -> Redirect {
+    let result = state
+        .pins
+        .update(|pins| {
+            pins.retain(|&pin| pin != id);
+            if pinned {
+                pins.push(id);
+            }
+        })
+        .await;
+
+    let message = match (result, pinned) {
+        (Ok(()), true) => FlashMessage::success("Note pinned"),
+        (Ok(()), false) => FlashMessage::success("Note unpinned"),
+        (Err(e), _) => FlashMessage::error(format!("Failed to save pins: {}", e)),
+    };
+    session.set_flash(message).await.unwrap();
+
+    redirect(&format!("/note/{id}"))
+}
+
+pub async fn route_pin_note(
+    session: Session,
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> Redirect {
+    set_pinned(session, state, id, true).await
+}
+
+pub async fn route_unpin_note(
+    session: Session,
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> Redirect {
+    set_pinned(session, state, id, false).await
+}
+
+/// Reorder the pins after a drag in the sidebar.
+///
+/// Ids that aren't pinned are ignored, and pins missing from the
+/// request keep their place at the end, so a stale page can't drop any.
+pub async fn route_reorder_pins(
+    State(state): State<AppState>,
+    Json(order): Json<PinOrder>,
+) -> Response {
+    let result = state
+        .pins
+        .update(|pins| {
+            let mut reordered = Pins::new();
+            for id in order.ids.iter().chain(pins.iter()) {
+                if pins.contains(id) && !reordered.contains(id) {
+                    reordered.push(*id);
+                }
+            }
+            *pins = reordered;
+        })
+        .await;
+
+    match result {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => {
+            error!(error = ?e, "Failed to save pin order");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
@@ -1,65 +1,99 @@
 use axum::{
     extract::{Path, State, Query},
-    response::{Html, Redirect, Response, IntoResponse},
+    response::{Html, Redirect},
     Form,
 };
 use draftsmith_rest_api::client::tags::{list_tags, list_note_tags, attach_tag_to_note, detach_tag_from_note};
 use crate::state::AppState;
 use crate::flash::{FlashMessage, FlashMessageStore};
+use crate::routes::tags::create::create_named_tag;
 use crate::template_context::{NoteTemplateContext, PaginationParams};
 use minijinja::context;
 use serde::Deserialize;
 use tower_sessions::Session;
-use crate::templates::{handle_not_found, handle_template_error, ENV};
+use crate::error::AppError;
+use crate::templates::render_page;
+use crate::urls::redirect;
+use crate::upstream;
+use tracing::error;
 
 #[derive(Debug, Deserialize)]
 pub struct TagActionForm {
-    tag_id: i32,
+    tag_id: Option<String>, // String to handle the empty hidden input from the suggest box
+    tag_name: Option<String>, // Used when no tag_id was picked, created if missing
     action: String, // "attach" or "detach"
 }
 
+/// Find a tag by name, ignoring case like the suggest box does,
+/// creating it if there isn't one. Returns the tag id and whether it was created.
+///
+/// Tags in different branches may share a name, the user has to pick
+/// one of those from the suggestions rather than have one guessed.
+async fn find_or_create_tag(api_addr: &str, name: &str) -> Result<(i32, bool), String> {
+    let name = name.trim();
+    let tags = upstream::call("list_tags", list_tags(api_addr))
+        .await
+        .map_err(|e| format!("Failed to get tags: {}", e))?;
+
+    let lowercase_name = name.to_lowercase();
+    let matches: Vec<_> = tags
+        .iter()
+        .filter(|tag| tag.name.to_lowercase() == lowercase_name)
+        .collect();
+    match matches.as_slice() {
+        [] => {}
+        [tag] => return Ok((tag.id, false)),
+        _ => {
+            return Err(format!(
+                "{} tags are named '{}', pick one from the suggestions",
+                matches.len(),
+                name
+            ));
+        }
+    }
+
+    let id = create_named_tag(api_addr, name)
+        .await
+        .map_err(|e| format!("Failed to create tag: {}", e))?;
+    Ok((id, true))
+}
+
 pub async fn route_assign_tags_get(
     session: Session,
     State(state): State<AppState>,
     Path(note_id): Path<i32>,
     Query(params): Query<PaginationParams>,
-) -> Response {
+) -> Result<Html<String>, AppError> {
     let api_addr = state.api_addr.clone();
 
     // Get note data
-    let note_handler =
-        match NoteTemplateContext::new(session.clone(), Query(params), api_addr.clone(), note_id).await {
-            Ok(data) => data,
-            Err(e) => {
-                eprintln!("Failed to get note data: {:#}", e);
-                return handle_not_found(session).await.into_response();
-            }
-        };
+    let note_handler = NoteTemplateContext::new(session, Query(params), &state, note_id)
+        .await
+        .map_err(|e| {
+            AppError::upstream(format!("Unable to load note #{}", note_id), e)
+                .or_not_found(format!("Note #{} does not exist", note_id))
+        })?;
 
 
     // Get all available tags
-    let all_tags = list_tags(&api_addr)
+    let all_tags = upstream::call("list_tags", list_tags(&api_addr))
         .await
         .unwrap_or_else(|e| {
-            eprintln!("Failed to get tags: {:#?}", e);
+            error!(error = ?e, "Failed to get tags");
             vec![]
         });
 
     // Get current note's tags
-    let note_tags = list_note_tags(&api_addr)
+    let note_tags = upstream::call("list_note_tags", list_note_tags(&api_addr))
         .await
         .unwrap_or_else(|e| {
-            eprintln!("Failed to get note tags: {:#?}", e);
+            error!(error = ?e, "Failed to get note tags");
             vec![]
         })
         .into_iter()
         .filter(|nt| nt.note_id == note_id)
         .collect::<Vec<_>>();
 
-    let template = ENV.get_template("body/note/assign_tags.html").unwrap_or_else(|e| {
-        panic!("Failed to load template. Error: {:#}", e);
-    });
-
 
     let ctx = context! { ..note_handler.ctx, ..context! {
         note_id => note_id,
@@ -67,9 +101,7 @@ pub async fn route_assign_tags_get(
         note_tags => note_tags,
     }};
 
-
-    let rendered = template.render(ctx).unwrap_or_else(handle_template_error);
-    Html(rendered).into_response()
+    render_page("body/note/assign_tags.html", ctx)
 }
 
 pub async fn route_assign_tags_post(
@@ -80,34 +112,59 @@ pub async fn route_assign_tags_post(
 ) -> Redirect {
     let api_addr = state.api_addr.clone();
 
-    let result: Result<(), _> = match form.action.as_str() {
-        "attach" => {
-            attach_tag_to_note(&api_addr, note_id, form.tag_id).await.map(|_| ())
-        }
-        "detach" => detach_tag_from_note(&api_addr, note_id, form.tag_id).await,
+    let tag_id = form
+        .tag_id
+        .filter(|s| !s.is_empty())
+        .and_then(|s| s.parse::<i32>().ok());
+
+    let result: Result<String, String> = match (form.action.as_str(), tag_id) {
+        ("attach", Some(tag_id)) => upstream::call("attach_tag_to_note", attach_tag_to_note(&api_addr, note_id, tag_id))
+            .await
+            .map(|_| String::from("Tags updated successfully"))
+            .map_err(|e| format!("Failed to update tags: {}", e)),
+        ("attach", None) => match form.tag_name.as_deref().filter(|n| !n.trim().is_empty()) {
+            Some(name) => match find_or_create_tag(&api_addr, name).await {
+                Ok((tag_id, created)) => upstream::call("attach_tag_to_note", attach_tag_to_note(&api_addr, note_id, tag_id))
+                    .await
+                    .map(|_| {
+                        if created {
+                            format!("Created tag '{}' and attached it", name.trim())
+                        } else {
+                            String::from("Tags updated successfully")
+                        }
+                    })
+                    .map_err(|e| format!("Failed to update tags: {}", e)),
+                Err(e) => Err(e),
+            },
+            None => Err(String::from("Enter a tag name")),
+        },
+        ("detach", Some(tag_id)) => upstream::call("detach_tag_from_note", detach_tag_from_note(&api_addr, note_id, tag_id))
+            .await
+            .map(|_| String::from("Tags updated successfully"))
+            .map_err(|e| format!("Failed to update tags: {}", e)),
         _ => {
             session
                 .set_flash(FlashMessage::error("Invalid action specified"))
                 .await
                 .unwrap();
-            return Redirect::to(&format!("/assign_tags/{}", note_id));
+            return redirect(&format!("/assign_tags/{}", note_id));
         }
     };
 
     match result {
-        Ok(_) => {
+        Ok(message) => {
             session
-                .set_flash(FlashMessage::success("Tags updated successfully"))
+                .set_flash(FlashMessage::success(message))
                 .await
                 .unwrap();
         }
         Err(e) => {
             session
-                .set_flash(FlashMessage::error(format!("Failed to update tags: {}", e)))
+                .set_flash(FlashMessage::error(e))
                 .await
                 .unwrap();
         }
     }
 
-    Redirect::to(&format!("/assign_tags/{}", note_id))
+    redirect(&format!("/assign_tags/{}", note_id))
 }
@@ -1,52 +1,60 @@
 use crate::state::AppState;
 use crate::template_context::{NoteTemplateContext, PaginationParams};
-use crate::templates::{handle_not_found, handle_template_error, ENV};
+use crate::error::AppError;
+use crate::history::ViewHistory;
+use crate::templates::render_page;
+use crate::urls::prefix_rendered_links;
 use axum::{
     extract::{Path, Query, State},
-    response::{Html, IntoResponse, Response},
+    response::Html,
 };
 use minijinja::context;
+use serde::Deserialize;
 use tower_sessions::Session;
+use tracing::error;
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ViewParams {
+    /// Set when stepping through the history with Alt+Left/Right,
+    /// so the step isn't recorded as a new view
+    #[serde(default)]
+    pub history: bool,
+}
 
 pub async fn route_note(
     session: Session,
     State(state): State<AppState>,
     Path(id): Path<i32>,
     Query(params): Query<PaginationParams>,
-) -> Response {
-    let api_addr: String = state.api_addr.clone();
+    Query(view): Query<ViewParams>,
+) -> Result<Html<String>, AppError> {
     // Get note data
-    let note_handler =
-        match NoteTemplateContext::new(session.clone(), Query(params), api_addr, id).await {
-            Ok(data) => data,
-            Err(e) => {
-                eprintln!("Failed to get note data: {:#}", e);
-                return handle_not_found(session).await.into_response();
-            }
-        };
+    let note_handler = NoteTemplateContext::new(session.clone(), Query(params), &state, id)
+        .await
+        .map_err(|e| {
+            AppError::upstream(format!("Unable to load note #{}", id), e)
+                .or_not_found(format!("Note #{} does not exist", id))
+        })?;
 
     // Get rendered HTML
-    let rendered_note = match note_handler.get_rendered_html(id).await {
-        Ok(html) => html,
-        Err(e) => {
-            eprintln!("Failed to get rendered note: {:#}", e);
-            return Html(String::from("<h1>Error rendering note</h1>")).into_response();
-        }
-    };
+    let rendered_note = note_handler
+        .get_rendered_html(id)
+        .await
+        .map(|html| prefix_rendered_links(&html))
+        .map_err(|e| {
+            AppError::upstream("Unable to render this note", e)
+                .with_layout(note_handler.ctx.clone())
+        })?;
 
-    // Load and render template
-    let template = ENV.get_template("body/note/read.html").unwrap_or_else(|e| {
-        panic!("Failed to load template. Error: {:#}", e);
-    });
+    if !view.history {
+        if let Err(e) = session.record_view(id, &note_handler.title).await {
+            error!(error = ?e, "Failed to record view");
+        }
+    }
 
     let ctx = context! { ..note_handler.ctx, ..context! {
         rendered_note => rendered_note,
     }};
 
-    let rendered = match template.render(ctx) {
-        Ok(result) => result,
-        Err(err) => handle_template_error(err),
-    };
-
-    Html(rendered).into_response()
+    render_page("body/note/read.html", ctx)
 }
@@ -0,0 +1,150 @@
+use crate::error::AppError;
+use crate::flash::{FlashMessage, FlashMessageStore};
+use crate::prefs::PreferenceStore;
+use crate::state::AppState;
+use crate::template_context::{BodyTemplateContext, PaginationParams};
+use crate::templates::render_page;
+use crate::urls;
+use crate::MAX_ITEMS_PER_PAGE;
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::{Html, IntoResponse, Redirect, Response},
+    Form, Json,
+};
+use chrono_tz::{Tz, TZ_VARIANTS};
+use minijinja::context;
+use serde::Deserialize;
+use tower_sessions::Session;
+use tracing::error;
+
+#[derive(Debug, Deserialize)]
+pub struct TreePageSizeForm {
+    pub page_size: usize,
+    pub redirect_to: Option<String>,
+}
+
+pub async fn route_set_tree_page_size(
+    session: Session,
+    Form(form): Form<TreePageSizeForm>,
+) -> Redirect {
+    let redirect = urls::safe_redirect(form.redirect_to.as_deref(), "/");
+
+    if form.page_size == 0 || form.page_size > MAX_ITEMS_PER_PAGE {
+        session
+            .set_flash(FlashMessage::error(format!(
+                "Tree page size must be between 1 and {}",
+                MAX_ITEMS_PER_PAGE
+            )))
+            .await
+            .unwrap();
+        return redirect;
+    }
+
+    let result = match session.prefs().await {
+        Ok(mut prefs) => {
+            prefs.tree_page_size = Some(form.page_size);
+            session.set_prefs(prefs).await
+        }
+        Err(e) => Err(e),
+    };
+    if let Err(e) = result {
+        error!(error = ?e, "Failed to store preferences");
+        session
+            .set_flash(FlashMessage::error("Failed to save the tree page size"))
+            .await
+            .unwrap();
+    }
+
+    redirect
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TreeExpandedForm {
+    pub id: i32,
+    pub open: bool,
+}
+
+/// Remember a sidebar note the user expanded or collapsed,
+/// sent by `tree_controller.js` so the next page renders it the same way
+pub async fn route_set_tree_expanded(
+    session: Session,
+    Json(form): Json<TreeExpandedForm>,
+) -> Response {
+    let result = match session.prefs().await {
+        Ok(mut prefs) => {
+            prefs.set_expanded(form.id, form.open);
+            session.set_prefs(prefs).await
+        }
+        Err(e) => Err(e),
+    };
+
+    match result {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => {
+            error!(error = ?e, "Failed to store preferences");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TimezoneForm {
+    /// Empty to go back to the server's timezone
+    pub timezone: String,
+}
+
+pub async fn route_preferences(
+    session: Session,
+    State(state): State<AppState>,
+    Query(params): Query<PaginationParams>,
+) -> Result<Html<String>, AppError> {
+    let prefs = session.prefs().await.unwrap_or_default();
+    let body_handler = BodyTemplateContext::new(session, Query(params), &state, None)
+        .await
+        .map_err(|e| AppError::upstream("Unable to reach the Draftsmith API", e))?;
+
+    let ctx = context! { ..body_handler.ctx, ..context! {
+        user_timezone => prefs.timezone,
+        default_timezone => state.config.server.timezone().name(),
+        timezones => TZ_VARIANTS.iter().map(|tz| tz.name()).collect::<Vec<_>>(),
+    }};
+
+    render_page("body/preferences.html", ctx)
+}
+
+pub async fn route_set_timezone(session: Session, Form(form): Form<TimezoneForm>) -> Redirect {
+    let name = form.timezone.trim();
+    let timezone = if name.is_empty() {
+        None
+    } else {
+        match name.parse::<Tz>() {
+            Ok(tz) => Some(tz.name().to_string()),
+            Err(_) => {
+                session
+                    .set_flash(FlashMessage::error(format!("Unknown timezone '{}'", name)))
+                    .await
+                    .unwrap();
+                return urls::redirect("/preferences");
+            }
+        }
+    };
+
+    let result = match session.prefs().await {
+        Ok(mut prefs) => {
+            prefs.timezone = timezone;
+            session.set_prefs(prefs).await
+        }
+        Err(e) => Err(e),
+    };
+    let message = match result {
+        Ok(()) => FlashMessage::success("Timezone saved"),
+        Err(e) => {
+            error!(error = ?e, "Failed to store preferences");
+            FlashMessage::error("Failed to save the timezone")
+        }
+    };
+    session.set_flash(message).await.unwrap();
+
+    urls::redirect("/preferences")
+}
@@ -1,65 +1,202 @@
+use crate::datetime::DayGroup;
+use crate::html_builder::find_node;
+use crate::listing::{empty_as_none, sort_notes, ListParams, SortField};
+use crate::prefs::PreferenceStore;
+use crate::routes::tree::load_tree;
 use crate::state::AppState;
 use crate::template_context::{BodyTemplateContext, PaginationParams};
-use crate::templates::{handle_template_error, ENV};
+use crate::error::AppError;
+use crate::templates::render_page;
 use axum::{
     extract::{Query, State},
     response::Html,
 };
+use chrono::Utc;
 use draftsmith_rest_api::client::notes::{fetch_notes, get_note_path};
-use minijinja::context;
+use draftsmith_rest_api::client::tags::{list_note_tags, list_tags};
+use draftsmith_rest_api::client::NoteTreeNode;
+use futures::stream::{self, StreamExt};
+use minijinja::{context, Value};
+use serde::Deserialize;
+use std::collections::HashSet;
 use tower_sessions::Session;
+use crate::upstream;
+use tracing::error;
+
+/// How many note paths are fetched from the API at once
+const MAX_CONCURRENT_PATHS: usize = 8;
+
+#[derive(Debug, Default, Deserialize)]
+pub struct RecentFilters {
+    /// Only notes in the subtree of this note, including itself
+    #[serde(default, deserialize_with = "empty_as_none")]
+    pub under: Option<i32>,
+    /// Only notes with this tag
+    #[serde(default, deserialize_with = "empty_as_none")]
+    pub tag: Option<i32>,
+}
+
+impl RecentFilters {
+    /// Carried along by the sort and page links of `list_controls.html`
+    fn query(&self) -> String {
+        let mut query = String::new();
+        if let Some(under) = self.under {
+            query.push_str(&format!("&under={}", under));
+        }
+        if let Some(tag) = self.tag {
+            query.push_str(&format!("&tag={}", tag));
+        }
+        query
+    }
+}
+
+fn collect_ids(node: &NoteTreeNode, ids: &mut HashSet<i32>) {
+    ids.insert(node.id);
+    for child in &node.children {
+        collect_ids(child, ids);
+    }
+}
 
-// TODO implement recent
 pub async fn route_recent(
     session: Session,
     State(state): State<AppState>,
     Query(params): Query<PaginationParams>,
-) -> Html<String> {
+    Query(list_params): Query<ListParams>,
+    Query(filters): Query<RecentFilters>,
+) -> Result<Html<String>, AppError> {
     let api_addr: String = state.api_addr.clone();
+    // Days are grouped in the reader's timezone
+    let session_timezone = session
+        .prefs()
+        .await
+        .unwrap_or_default()
+        .display_timezone(state.config.server.timezone());
     // Get the body data
-    let body_handler =
-        match BodyTemplateContext::new(session, Query(params), api_addr.clone(), None).await {
-            Ok(handler) => handler,
-            Err(e) => {
-                eprintln!("Failed to create body handler: {:#?}", e);
-                return Html(String::from("<h1>Error getting page data</h1>"));
-            }
-        };
+    let body_handler = BodyTemplateContext::new(session, Query(params), &state, None)
+        .await
+        .map_err(|e| AppError::upstream("Unable to reach the Draftsmith API", e))?;
 
     // Get Recent notes
     let metadata_only = true;
-    let mut notes = match fetch_notes(&api_addr, metadata_only).await {
-        Ok(notes) => notes,
+    let mut notes = upstream::call("fetch_notes", fetch_notes(&api_addr, metadata_only))
+        .await
+        .map_err(|e| {
+            AppError::upstream("Unable to fetch notes", e).with_layout(body_handler.ctx.clone())
+        })?;
+
+    // The top-level notes double as the choices for the subtree filter,
+    // which can't be applied without the tree
+    let tree = match load_tree(&state).await {
+        Ok(tree) => tree,
+        Err(e) if filters.under.is_some() => return Err(e.with_layout(body_handler.ctx.clone())),
         Err(e) => {
-            eprintln!("Failed to fetch notes: {:#?}", e);
-            return Html(String::from("<h1>Error fetching notes</h1>"));
+            error!(error = ?e, "Failed to get note tree");
+            Vec::new()
         }
     };
 
-    // Sort notes by updated_at
-    notes.sort_by(|a, b| a.modified_at.cmp(&b.modified_at));
+    if let Some(under) = filters.under {
+        let (node, _) = find_node(&tree, under).ok_or_else(|| {
+            AppError::not_found(format!("Note #{} does not exist", under))
+                .with_layout(body_handler.ctx.clone())
+        })?;
+        let mut ids = HashSet::new();
+        collect_ids(node, &mut ids);
+        notes.retain(|note| ids.contains(&note.id));
+    }
 
-    // Include only the last 50 notes
-    let mut recent_notes = notes.into_iter().rev().take(50).collect::<Vec<_>>();
-    for note in &mut recent_notes {
-        note.title= get_note_path(&api_addr, note.id)
+    if let Some(tag) = filters.tag {
+        let ids: HashSet<i32> = upstream::call("list_note_tags", list_note_tags(&api_addr))
             .await
-            .unwrap_or_else(|e| {
-                eprintln!("Failed to get note path: {:#?}", e);
-                note.title.clone()
-            });
+            .map_err(|e| {
+                AppError::upstream("Unable to fetch the notes for this tag", e)
+                    .with_layout(body_handler.ctx.clone())
+            })?
+            .into_iter()
+            .filter(|nt| nt.tag_id == tag)
+            .map(|nt| nt.note_id)
+            .collect();
+        notes.retain(|note| ids.contains(&note.id));
     }
 
-    let template = ENV.get_template("body/recent.html").unwrap_or_else(|e| {
-        panic!("Failed to load template. Error: {:#}", e);
-    });
+    let tags = upstream::call("list_tags", list_tags(&api_addr))
+        .await
+        .unwrap_or_else(|e| {
+            error!(error = ?e, "Failed to get tags");
+            Vec::new()
+        });
+
+    // Recent means by time, a title sort falls back to modified
+    let sort = match list_params.sort() {
+        SortField::Title => SortField::Modified,
+        sort => sort,
+    };
+    let list_params = ListParams {
+        sort: Some(sort),
+        ..list_params
+    };
+    sort_notes(&mut notes, sort, list_params.order());
+    let listing = list_params.paginate(notes);
+
+    // Show the full path of only the notes on this page, a few requests at a time
+    let paths: Vec<String> = stream::iter(&listing.items)
+        .map(|note| {
+            let api_addr = &api_addr;
+            async move {
+                upstream::call("get_note_path", get_note_path(api_addr, note.id))
+                    .await
+                    .unwrap_or_else(|e| {
+                        error!(error = ?e, "Failed to get note path");
+                        note.title.clone()
+                    })
+            }
+        })
+        .buffered(MAX_CONCURRENT_PATHS)
+        .collect()
+        .await;
+
+    let now = Utc::now();
+    let timezone = session_timezone;
+    let mut groups: Vec<(DayGroup, Vec<Value>)> = Vec::new();
+    for (note, path) in listing.items.iter().zip(paths) {
+        // The API sends times without an offset, which are UTC
+        let timestamp = match sort {
+            SortField::Created => note.created_at.and_utc(),
+            _ => note.modified_at.and_utc(),
+        };
+        let group = DayGroup::of(timestamp, now, timezone);
+        let row = context! {
+            note => note,
+            path => path,
+        };
+
+        // Notes are sorted by time, so each day forms one run
+        match groups.last_mut() {
+            Some((last, rows)) if *last == group => rows.push(row),
+            _ => groups.push((group, vec![row])),
+        }
+    }
+    let groups: Vec<Value> = groups
+        .into_iter()
+        .map(|(group, rows)| context! { label => group.label(), rows => rows })
+        .collect();
+
+    let roots: Vec<Value> = tree
+        .iter()
+        .map(|node| context! { id => node.id, title => node.title.as_deref().unwrap_or("Untitled") })
+        .collect();
 
     // get the context vars
     let ctx = context! { ..body_handler.ctx, ..context! {
-        notes => recent_notes,
+        listing => listing,
+        groups => groups,
+        filters => context! { under => filters.under, tag => filters.tag },
+        extra_query => filters.query(),
+        sort_fields => vec!["created", "modified"],
+        roots => roots,
+        all_tags => tags,
     }};
 
-    let rendered = template.render(ctx).unwrap_or_else(handle_template_error);
-
-    Html(rendered)
+    render_page("body/recent.html", ctx)
 }
+
@@ -1,6 +1,7 @@
 use crate::state::AppState;
 use crate::template_context::{BodyTemplateContext, PaginationParams};
-use crate::templates::{handle_template_error, ENV};
+use crate::error::AppError;
+use crate::templates::render_page;
 use axum::{
     extract::{Query, State},
     response::Html,
@@ -9,6 +10,8 @@ use draftsmith_rest_api::client::notes::{fetch_notes, fts_search_notes, get_note
 use minijinja::context;
 use serde::Deserialize;
 use tower_sessions::Session;
+use crate::upstream;
+use tracing::error;
 
 #[derive(Debug, Deserialize)]
 pub struct SearchParams {
@@ -21,49 +24,38 @@ pub async fn search(
     session: Session,
     State(state): State<AppState>,
     Query(params): Query<SearchParams>,
-) -> Html<String> {
+) -> Result<Html<String>, AppError> {
     let api_addr: String = state.api_addr.clone();
 
     // Get the body data
-    let body_handler =
-        match BodyTemplateContext::new(session, Query(params.pagination), api_addr.clone(), None)
-            .await
-        {
-            Ok(handler) => handler,
-            Err(e) => {
-                eprintln!("Failed to create body handler: {:#?}", e);
-                return Html(String::from("<h1>Error getting page data</h1>"));
-            }
-        };
+    let body_handler = BodyTemplateContext::new(session, Query(params.pagination), &state, None)
+        .await
+        .map_err(|e| AppError::upstream("Unable to reach the Draftsmith API", e))?;
 
     // Get notes based on whether we have a search term
     let notes = if let Some(ref search_term) = params.q {
-        match fts_search_notes(&api_addr, search_term).await {
-            Ok(notes) => notes,
-            Err(e) => {
-                eprintln!("Failed to search notes: {:#?}", e);
-                return Html(String::from("<h1>Error searching notes</h1>"));
-            }
-        }
+        upstream::call("fts_search_notes", fts_search_notes(&api_addr, search_term))
+            .await
+            .map_err(|e| {
+                AppError::upstream("Unable to search notes", e).with_layout(body_handler.ctx.clone())
+            })?
     } else {
         // If no search term, get recent notes
         let metadata_only = true;
-        match fetch_notes(&api_addr, metadata_only).await {
-            Ok(notes) => notes,
-            Err(e) => {
-                eprintln!("Failed to fetch notes: {:#?}", e);
-                return Html(String::from("<h1>Error fetching notes</h1>"));
-            }
-        }
+        upstream::call("fetch_notes", fetch_notes(&api_addr, metadata_only))
+            .await
+            .map_err(|e| {
+                AppError::upstream("Unable to fetch notes", e).with_layout(body_handler.ctx.clone())
+            })?
     };
 
     // Include only the last 50 notes
     let mut recent_notes = notes.into_iter().take(50).collect::<Vec<_>>();
     for note in &mut recent_notes {
-        note.title= get_note_path(&api_addr, note.id)
+        note.title= upstream::call("get_note_path", get_note_path(&api_addr, note.id))
             .await
             .unwrap_or_else(|e| {
-                eprintln!("Failed to get note path: {:#?}", e);
+                error!(error = ?e, "Failed to get note path");
                 note.title.clone()
             });
     }
@@ -87,12 +79,6 @@ pub async fn search(
     //         .unwrap_or(note.content.clone());
     // }
 
-    let template = ENV
-        .get_template("body/search_results.html")
-        .unwrap_or_else(|e| {
-            panic!("Failed to load template. Error: {:#}", e);
-        });
-
     // get the context vars
     let ctx = context! {
         ..body_handler.ctx,
@@ -102,7 +88,5 @@ pub async fn search(
         }
     };
 
-    let rendered = template.render(ctx).unwrap_or_else(handle_template_error);
-
-    Html(rendered)
+    render_page("body/search_results.html", ctx)
 }
@@ -8,13 +8,29 @@ use tower_sessions::Session;
 use draftsmith_rest_api::client::tags::{create_tag, CreateTagRequest};
 use crate::flash::{FlashMessage, FlashMessageStore};
 use crate::state::AppState;
+use crate::urls::redirect;
+use crate::upstream;
+
+/// Create a tag, returning its id.
+/// Shared by the tag management page and the assign-tags page.
+pub async fn create_named_tag(api_addr: &str, name: &str) -> Result<i32, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(String::from("Tag names can't be empty"));
+    }
+
+    upstream::call("create_tag", create_tag(api_addr, CreateTagRequest { name: name.to_string() }))
+        .await
+        .map(|tag| tag.id)
+        .map_err(|e| e.to_string())
+}
 
 pub async fn route_create_tag(
     session: Session,
     State(state): State<AppState>,
     Form(form): Form<CreateTagRequest>,
 ) -> Redirect {
-    match create_tag(&state.api_addr, form).await {
+    match create_named_tag(&state.api_addr, &form.name).await {
         Ok(_) => {
             session
                 .set_flash(FlashMessage::success("Tag created successfully"))
@@ -29,5 +45,5 @@ pub async fn route_create_tag(
         }
     }
 
-    Redirect::to("/manage_tags")
+    redirect("/manage_tags")
 }
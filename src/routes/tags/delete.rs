@@ -6,14 +6,20 @@ use tower_sessions::Session;
 use draftsmith_rest_api::client::tags::delete_tag;
 use crate::flash::{FlashMessage, FlashMessageStore};
 use crate::state::AppState;
+use crate::urls::redirect;
+use crate::upstream;
+use tracing::error;
 
 pub async fn route_delete_tag(
     session: Session,
     State(state): State<AppState>,
     Path(id): Path<i32>,
 ) -> Redirect {
-    match delete_tag(&state.api_addr, id).await {
+    match upstream::call("delete_tag", delete_tag(&state.api_addr, id)).await {
         Ok(_) => {
+            if let Err(e) = state.tag_styles.update(|styles| styles.remove(&id)).await {
+                error!(error = ?e, "Failed to remove tag style");
+            }
             session
                 .set_flash(FlashMessage::success("Tag deleted successfully"))
                 .await
@@ -27,5 +33,5 @@ pub async fn route_delete_tag(
         }
     }
 
-    Redirect::to("/manage_tags")
+    redirect("/manage_tags")
 }
@@ -1,85 +1,71 @@
+use crate::listing::{sort_notes, ListParams};
 use crate::state::AppState;
 use crate::template_context::{BodyTemplateContext, PaginationParams};
-use crate::templates::{handle_template_error, ENV};
+use crate::error::AppError;
+use crate::templates::render_page;
 use axum::{
     extract::{Path, Query, State},
     response::Html,
 };
 use draftsmith_rest_api::client::notes::fetch_notes;
-use draftsmith_rest_api::client::tags::{list_note_tags, list_tags};
+use draftsmith_rest_api::client::tags::{get_tag, list_note_tags};
 use minijinja::context;
+use std::collections::HashSet;
 use tower_sessions::Session;
+use crate::upstream;
 
-// TODO implement recent
 pub async fn route_list_tag(
     session: Session,
     State(state): State<AppState>,
     Path(id): Path<i32>,
     Query(params): Query<PaginationParams>,
-) -> Html<String> {
+    Query(list_params): Query<ListParams>,
+) -> Result<Html<String>, AppError> {
     let api_addr: String = state.api_addr.clone();
     // Get the body data
-    let body_handler =
-        match BodyTemplateContext::new(session, Query(params), api_addr.clone(), None).await {
-            Ok(handler) => handler,
-            Err(e) => {
-                eprintln!("Failed to create body handler: {:#?}", e);
-                return Html(String::from("<h1>Error getting page data</h1>"));
-            }
-        };
-
-    // Get the tag name
-    let tag = list_tags(&api_addr)
+    let body_handler = BodyTemplateContext::new(session, Query(params), &state, None)
         .await
-        .unwrap_or_else(|e| {
-            eprintln!("Failed to get tags: {:#?}", e);
-            vec![]
-        })
-        // Filter out the current tag
-        .into_iter()
-        .filter(|tag| tag.id == id)
-        .collect::<Vec<_>>();
+        .map_err(|e| AppError::upstream("Unable to reach the Draftsmith API", e))?;
 
-    let tag_name = match tag.first() {
-        Some(tag) => tag.name.clone(),
-        None => String::from("Unkn  "),
-    };
+    // Get the tag itself
+    let tag = upstream::call("get_tag", get_tag(&api_addr, id))
+        .await
+        .map_err(|e| {
+            AppError::upstream("Unable to fetch the tag from the Draftsmith API", e)
+                .or_not_found(format!("Tag #{} does not exist", id))
+                .with_layout(body_handler.ctx.clone())
+        })?;
 
-    // Get all tag Note Pairings
-    let tag_notes: Vec<_> = list_note_tags(&api_addr)
+    // Get the ids of notes with this tag
+    let note_ids: HashSet<i32> = upstream::call("list_note_tags", list_note_tags(&api_addr))
         .await
-        .unwrap_or_else(|e| {
-            eprintln!("Failed to get tags: {:#?}", e);
-            vec![]
-        })
-        // Filter out the relevant tags
+        .map_err(|e| {
+            AppError::upstream("Unable to fetch the notes for this tag", e)
+                .with_layout(body_handler.ctx.clone())
+        })?
         .into_iter()
-        .filter(|tag| tag.tag_id == id)
+        .filter(|nt| nt.tag_id == id)
+        .map(|nt| nt.note_id)
         .collect();
 
-    // Get all the note Metadata
-    let mut notes: Vec<_> = fetch_notes(&api_addr, true)
+    // One metadata request for all notes, rather than one per tagged note,
+    // so the list can be sorted by any field before it's paginated
+    let metadata_only = true;
+    let mut notes = upstream::call("fetch_notes", fetch_notes(&api_addr, metadata_only))
         .await
-        .expect("TODO don't panic")
-        .into_iter()
-        // Filter out the relevant notes for the tag id
-        .filter(|note| tag_notes.iter().any(|tag| tag.note_id == note.id))
-        .collect::<Vec<_>>();
-
-    // Sort notes by updated_at
-    notes.sort_by(|a, b| a.modified_at.cmp(&b.modified_at));
+        .map_err(|e| {
+            AppError::upstream("Unable to fetch notes", e).with_layout(body_handler.ctx.clone())
+        })?;
+    notes.retain(|note| note_ids.contains(&note.id));
 
-    let template = ENV.get_template("body/tags/list.html").unwrap_or_else(|e| {
-        panic!("Failed to load template. Error: {:#}", e);
-    });
+    sort_notes(&mut notes, list_params.sort(), list_params.order());
+    let listing = list_params.paginate(notes);
 
     // get the context vars
     let ctx = context! { ..body_handler.ctx, ..context! {
-        notes => notes,
-        tag_name => tag_name,
+        listing => listing,
+        tag_name => tag.name,
     }};
 
-    let rendered = template.render(ctx).unwrap_or_else(handle_template_error);
-
-    Html(rendered)
+    render_page("body/tags/list.html", ctx)
 }
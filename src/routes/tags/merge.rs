@@ -0,0 +1,155 @@
+use axum::{extract::State, response::Redirect};
+use axum_extra::extract::Form;
+use crate::flash::{FlashMessage, FlashMessageStore};
+use crate::state::AppState;
+use draftsmith_rest_api::client::tags::{
+    attach_child_tag, attach_tag_to_note, delete_tag, detach_child_tag, get_tag_tree,
+    list_note_tags, TagTreeNode,
+};
+use serde::Deserialize;
+use std::collections::HashSet;
+use tower_sessions::Session;
+use crate::urls::redirect;
+use crate::upstream;
+use tracing::error;
+
+#[derive(Debug, Deserialize)]
+pub struct MergeTagsForm {
+    /// Tags to merge away, repeated as `source_ids=1&source_ids=2`
+    #[serde(default)]
+    source_ids: Vec<i32>,
+    target_id: i32,
+}
+
+fn find_children(tree: &[TagTreeNode], tag_id: i32) -> Vec<i32> {
+    for node in tree {
+        if node.id == tag_id {
+            return node.children.iter().map(|child| child.id).collect();
+        }
+        let children = find_children(&node.children, tag_id);
+        if !children.is_empty() {
+            return children;
+        }
+    }
+    Vec::new()
+}
+
+/// Move every note and child tag from the source tags onto the target,
+/// then delete the sources.
+pub async fn route_merge_tags(
+    session: Session,
+    State(state): State<AppState>,
+    Form(form): Form<MergeTagsForm>,
+) -> Redirect {
+    let api_addr = &state.api_addr;
+    let target_id = form.target_id;
+    let sources: Vec<i32> = form
+        .source_ids
+        .into_iter()
+        .filter(|id| *id != target_id)
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+
+    if sources.is_empty() {
+        session
+            .set_flash(FlashMessage::error("Select at least one tag to merge into the target"))
+            .await
+            .unwrap();
+        return redirect("/manage_tags");
+    }
+
+    let note_tags = match upstream::call("list_note_tags", list_note_tags(api_addr)).await {
+        Ok(note_tags) => note_tags,
+        Err(e) => {
+            session
+                .set_flash(FlashMessage::error(format!("Failed to get note tags: {}", e)))
+                .await
+                .unwrap();
+            return redirect("/manage_tags");
+        }
+    };
+
+    let tag_tree = match upstream::call("get_tag_tree", get_tag_tree(api_addr)).await {
+        Ok(tree) => tree,
+        Err(e) => {
+            session
+                .set_flash(FlashMessage::error(format!("Failed to get tag tree: {}", e)))
+                .await
+                .unwrap();
+            return redirect("/manage_tags");
+        }
+    };
+
+    let mut tagged_with_target: HashSet<i32> = note_tags
+        .iter()
+        .filter(|nt| nt.tag_id == target_id)
+        .map(|nt| nt.note_id)
+        .collect();
+
+    let mut errors = Vec::new();
+    let mut notes_moved = 0;
+    let mut merged = 0;
+
+    for source_id in sources {
+        let mut source_ok = true;
+
+        // Move the note associations
+        for note_tag in note_tags.iter().filter(|nt| nt.tag_id == source_id) {
+            if !tagged_with_target.insert(note_tag.note_id) {
+                continue;
+            }
+            match upstream::call("attach_tag_to_note", attach_tag_to_note(api_addr, note_tag.note_id, target_id)).await {
+                Ok(_) => notes_moved += 1,
+                Err(e) => {
+                    source_ok = false;
+                    errors.push(format!("note #{}: {}", note_tag.note_id, e));
+                }
+            }
+        }
+
+        // Re-parent child tags so they aren't deleted along with the source
+        for child_id in find_children(&tag_tree, source_id) {
+            if let Err(e) = upstream::call("detach_child_tag", detach_child_tag(api_addr, child_id)).await {
+                source_ok = false;
+                errors.push(format!("child tag #{}: {}", child_id, e));
+                continue;
+            }
+            if child_id == target_id {
+                continue;
+            }
+            if let Err(e) = upstream::call("attach_child_tag", attach_child_tag(api_addr, target_id, child_id)).await {
+                source_ok = false;
+                errors.push(format!("child tag #{}: {}", child_id, e));
+            }
+        }
+
+        // Only delete the source once everything it held has a new home
+        if !source_ok {
+            errors.push(format!("tag #{} was kept", source_id));
+            continue;
+        }
+        match upstream::call("delete_tag", delete_tag(api_addr, source_id)).await {
+            Ok(_) => {
+                merged += 1;
+                if let Err(e) = state.tag_styles.update(|styles| styles.remove(&source_id)).await {
+                    error!(error = ?e, "Failed to remove tag style");
+                }
+            }
+            Err(e) => errors.push(format!("deleting tag #{}: {}", source_id, e)),
+        }
+    }
+
+    let summary = format!(
+        "Merged {} tag(s), moving {} note association(s)",
+        merged, notes_moved
+    );
+    let flash = if errors.is_empty() {
+        FlashMessage::success(summary)
+    } else {
+        FlashMessage::warning(format!("{}. Problems: {}", summary, errors.join("; ")))
+    };
+    session.set_flash(flash).await.unwrap();
+
+    redirect("/manage_tags")
+}
@@ -4,3 +4,7 @@ pub mod delete;
 pub mod update;
 pub mod notes;
 pub mod list;
+pub mod query;
+pub mod merge;
+pub mod style;
+pub mod suggest;
@@ -1,6 +1,7 @@
 use crate::state::AppState;
 use crate::template_context::{BodyTemplateContext, PaginationParams};
-use crate::templates::{handle_template_error, ENV};
+use crate::error::AppError;
+use crate::templates::render_page;
 use axum::{
     extract::{Query, State},
     response::Html,
@@ -8,33 +9,28 @@ use axum::{
 use draftsmith_rest_api::client::notes::{fetch_notes, get_note_path};
 use minijinja::context;
 use tower_sessions::Session;
+use crate::upstream;
+use tracing::error;
 
 // TODO implement recent
 pub async fn route_recent(
     session: Session,
     State(state): State<AppState>,
     Query(params): Query<PaginationParams>,
-) -> Html<String> {
+) -> Result<Html<String>, AppError> {
     let api_addr: String = state.api_addr.clone();
     // Get the body data
-    let body_handler =
-        match BodyTemplateContext::new(session, Query(params), api_addr.clone(), None).await {
-            Ok(handler) => handler,
-            Err(e) => {
-                eprintln!("Failed to create body handler: {:#?}", e);
-                return Html(String::from("<h1>Error getting page data</h1>"));
-            }
-        };
+    let body_handler = BodyTemplateContext::new(session, Query(params), &state, None)
+        .await
+        .map_err(|e| AppError::upstream("Unable to reach the Draftsmith API", e))?;
 
     // Get Recent notes
     let metadata_only = true;
-    let mut notes = match fetch_notes(&api_addr, metadata_only).await {
-        Ok(notes) => notes,
-        Err(e) => {
-            eprintln!("Failed to fetch notes: {:#?}", e);
-            return Html(String::from("<h1>Error fetching notes</h1>"));
-        }
-    };
+    let mut notes = upstream::call("fetch_notes", fetch_notes(&api_addr, metadata_only))
+        .await
+        .map_err(|e| {
+            AppError::upstream("Unable to fetch notes", e).with_layout(body_handler.ctx.clone())
+        })?;
 
     // Sort notes by updated_at
     notes.sort_by(|a, b| a.modified_at.cmp(&b.modified_at));
@@ -42,24 +38,18 @@ pub async fn route_recent(
     // Include only the last 50 notes
     let mut recent_notes = notes.into_iter().rev().take(50).collect::<Vec<_>>();
     for note in &mut recent_notes {
-        note.title= get_note_path(&api_addr, note.id)
+        note.title= upstream::call("get_note_path", get_note_path(&api_addr, note.id))
             .await
             .unwrap_or_else(|e| {
-                eprintln!("Failed to get note path: {:#?}", e);
+                error!(error = ?e, "Failed to get note path");
                 note.title.clone()
             });
     }
 
-    let template = ENV.get_template("body/recent.html").unwrap_or_else(|e| {
-        panic!("Failed to load template. Error: {:#}", e);
-    });
-
     // get the context vars
     let ctx = context! { ..body_handler.ctx, ..context! {
         recent_notes => recent_notes,
     }};
 
-    let rendered = template.render(ctx).unwrap_or_else(handle_template_error);
-
-    Html(rendered)
+    render_page("body/recent.html", ctx)
 }
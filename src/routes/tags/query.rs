@@ -0,0 +1,101 @@
+use crate::state::AppState;
+use crate::tag_query::TagQuery;
+use crate::template_context::{BodyTemplateContext, PaginationParams};
+use crate::error::AppError;
+use crate::templates::render_page;
+use axum::{
+    extract::{Query, State},
+    response::Html,
+};
+use draftsmith_rest_api::client::notes::{fetch_notes, NoteWithoutFts};
+use draftsmith_rest_api::client::tags::{get_tag_tree, list_note_tags};
+use minijinja::context;
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use tower_sessions::Session;
+use crate::upstream;
+
+#[derive(Debug, Deserialize)]
+pub struct TagQueryParams {
+    #[serde(flatten)]
+    pagination: PaginationParams,
+    q: Option<String>,
+    /// Checkbox: match every tag's descendants, as if each term had a `*`
+    #[serde(default)]
+    descendants: Option<String>,
+}
+
+pub async fn route_query_tags(
+    session: Session,
+    State(state): State<AppState>,
+    Query(params): Query<TagQueryParams>,
+) -> Result<Html<String>, AppError> {
+    let api_addr: String = state.api_addr.clone();
+
+    // Get the body data
+    let body_handler = BodyTemplateContext::new(session, Query(params.pagination), &state, None)
+        .await
+        .map_err(|e| AppError::upstream("Unable to reach the Draftsmith API", e))?;
+
+    let query_string = params.q.unwrap_or_default();
+    let all_descendants = params.descendants.is_some();
+
+    let mut notes = Vec::new();
+    let mut query_error = None;
+
+    if !query_string.trim().is_empty() {
+        match run_tag_query(&api_addr, &query_string, all_descendants).await {
+            Ok(found) => notes = found,
+            Err(e) => query_error = Some(e),
+        }
+    }
+
+    // get the context vars
+    let ctx = context! { ..body_handler.ctx, ..context! {
+        notes => notes,
+        query => query_string,
+        descendants => all_descendants,
+        query_error => query_error,
+    }};
+
+    render_page("body/tags/query.html", ctx)
+}
+
+async fn run_tag_query(
+    api_addr: &str,
+    query_string: &str,
+    all_descendants: bool,
+) -> Result<Vec<NoteWithoutFts>, String> {
+    let query = TagQuery::parse(query_string)?;
+
+    let tag_tree = upstream::call("get_tag_tree", get_tag_tree(api_addr))
+        .await
+        .map_err(|e| format!("Failed to get tag tree: {}", e))?;
+    let query = query.resolve(&tag_tree, all_descendants)?;
+
+    // Group the note-tag pairs by note
+    let mut tags_by_note: HashMap<i32, HashSet<i32>> = HashMap::new();
+    for note_tag in upstream::call("list_note_tags", list_note_tags(api_addr))
+        .await
+        .map_err(|e| format!("Failed to get note tags: {}", e))?
+    {
+        tags_by_note
+            .entry(note_tag.note_id)
+            .or_default()
+            .insert(note_tag.tag_id);
+    }
+
+    // Every note is a candidate, so `NOT archived` can match untagged notes
+    let untagged = HashSet::new();
+    let mut notes: Vec<_> = upstream::call("fetch_notes", fetch_notes(api_addr, true))
+        .await
+        .map_err(|e| format!("Failed to fetch notes: {}", e))?
+        .into_iter()
+        .filter(|note| query.matches(tags_by_note.get(&note.id).unwrap_or(&untagged)))
+        .collect();
+
+    // Most recently modified first
+    notes.sort_by(|a, b| b.modified_at.cmp(&a.modified_at));
+
+    Ok(notes)
+}
@@ -0,0 +1,115 @@
+use axum::{
+    extract::{Path, State},
+    response::Redirect,
+    Form,
+};
+use crate::flash::{FlashMessage, FlashMessageStore};
+use crate::state::AppState;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tower_sessions::Session;
+use crate::urls::redirect;
+
+/// The API has no field for tag colors, so the web app keeps them itself
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct TagStyle {
+    /// A hex color, e.g. "#e11d48"
+    pub color: Option<String>,
+    /// A short icon, usually an emoji
+    pub icon: Option<String>,
+}
+
+pub type TagStyles = HashMap<i32, TagStyle>;
+
+const MAX_ICON_CHARS: usize = 4;
+
+#[derive(Debug, Deserialize)]
+pub struct TagStyleForm {
+    color: Option<String>,
+    /// Checkbox, color pickers always submit a value
+    use_color: Option<String>,
+    icon: Option<String>,
+    /// "save" or "clear"
+    action: Option<String>,
+}
+
+fn parse_color(color: Option<String>) -> Result<Option<String>, String> {
+    let Some(color) = color.map(|c| c.trim().to_string()).filter(|c| !c.is_empty()) else {
+        return Ok(None);
+    };
+
+    let is_hex = color.len() == 7
+        && color.starts_with('#')
+        && color[1..].chars().all(|c| c.is_ascii_hexdigit());
+    if !is_hex {
+        return Err(format!("Invalid color '{}', expected e.g. #e11d48", color));
+    }
+
+    Ok(Some(color.to_lowercase()))
+}
+
+fn parse_icon(icon: Option<String>) -> Result<Option<String>, String> {
+    let Some(icon) = icon.map(|i| i.trim().to_string()).filter(|i| !i.is_empty()) else {
+        return Ok(None);
+    };
+
+    if icon.chars().count() > MAX_ICON_CHARS {
+        return Err(format!("Icons can be at most {} characters", MAX_ICON_CHARS));
+    }
+
+    Ok(Some(icon))
+}
+
+pub async fn route_set_tag_style(
+    session: Session,
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+    Form(form): Form<TagStyleForm>,
+) -> Redirect {
+    let style = if form.action.as_deref() == Some("clear") {
+        None
+    } else {
+        let color = form.use_color.and(form.color);
+        match parse_color(color).and_then(|color| {
+            parse_icon(form.icon).map(|icon| TagStyle { color, icon })
+        }) {
+            Ok(style) => Some(style),
+            Err(e) => {
+                session
+                    .set_flash(FlashMessage::error(e))
+                    .await
+                    .unwrap();
+                return redirect("/manage_tags");
+            }
+        }
+    };
+
+    let result = state
+        .tag_styles
+        .update(|styles| match style {
+            Some(style) if style.color.is_some() || style.icon.is_some() => {
+                styles.insert(id, style);
+            }
+            _ => {
+                styles.remove(&id);
+            }
+        })
+        .await;
+
+    match result {
+        Ok(_) => {
+            session
+                .set_flash(FlashMessage::success("Tag style updated successfully"))
+                .await
+                .unwrap();
+        }
+        Err(e) => {
+            session
+                .set_flash(FlashMessage::error(format!("Failed to save tag style: {}", e)))
+                .await
+                .unwrap();
+        }
+    }
+
+    redirect("/manage_tags")
+}
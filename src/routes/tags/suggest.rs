@@ -0,0 +1,71 @@
+use crate::routes::note_index::{flatten_tag_tree, IndexEntry};
+use crate::state::AppState;
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use draftsmith_rest_api::client::tags::get_tag_tree;
+use serde::Deserialize;
+use crate::upstream;
+use tracing::error;
+
+const MAX_SUGGESTIONS: usize = 20;
+
+#[derive(Debug, Deserialize)]
+pub struct SuggestParams {
+    #[serde(default)]
+    q: String,
+}
+
+/// Suggest tags whose name contains `q`, best matches first.
+///
+/// Each suggestion carries its `path` in the tag hierarchy,
+/// e.g. "project / urgent", so tags with the same name can be told apart.
+pub async fn route_suggest_tags(
+    State(state): State<AppState>,
+    Query(params): Query<SuggestParams>,
+) -> Response {
+    let tag_tree = match upstream::call("get_tag_tree", get_tag_tree(&state.api_addr)).await {
+        Ok(tree) => tree,
+        Err(e) => {
+            error!(error = ?e, "Failed to get tag tree");
+            return (
+                StatusCode::BAD_GATEWAY,
+                Json(serde_json::json!({ "error": "Unable to fetch tags" })),
+            )
+                .into_response();
+        }
+    };
+
+    let mut tags = Vec::new();
+    flatten_tag_tree(&tag_tree, "", &mut tags);
+
+    let query = params.q.trim().to_lowercase();
+
+    // Exact matches, then prefix matches, then anything containing the query
+    let rank = |tag: &IndexEntry| {
+        let name = tag.title.to_lowercase();
+        if name == query {
+            0
+        } else if name.starts_with(&query) {
+            1
+        } else {
+            2
+        }
+    };
+
+    let mut suggestions: Vec<IndexEntry> = tags
+        .into_iter()
+        .filter(|tag| tag.title.to_lowercase().contains(&query))
+        .collect();
+    suggestions.sort_by(|a, b| {
+        rank(a)
+            .cmp(&rank(b))
+            .then_with(|| a.path.to_lowercase().cmp(&b.path.to_lowercase()))
+    });
+    suggestions.truncate(MAX_SUGGESTIONS);
+
+    Json(suggestions).into_response()
+}
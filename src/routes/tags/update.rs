@@ -4,10 +4,12 @@ use axum::{
     Form,
 };
 use tower_sessions::Session;
-use draftsmith_rest_api::client::tags::{update_tag, UpdateTagRequest, attach_child_tag, detach_child_tag, get_tag_tree, TagTreeNode};
+use draftsmith_rest_api::client::tags::{update_tag, UpdateTagRequest, attach_child_tag, detach_child_tag, get_tag_tree, list_tags, TagTreeNode};
 use crate::flash::{FlashMessage, FlashMessageStore};
 use crate::state::AppState;
 use serde::Deserialize;
+use crate::urls::redirect;
+use crate::upstream;
 
 #[axum::debug_handler]
 pub async fn route_unset_parent(
@@ -16,14 +18,14 @@ pub async fn route_unset_parent(
     Path(child_id): Path<i32>,
 ) -> Redirect {
     // Get the full tag tree to check if tag has a parent
-    let tag_tree = match get_tag_tree(&state.api_addr).await {
+    let tag_tree = match upstream::call("get_tag_tree", get_tag_tree(&state.api_addr)).await {
         Ok(tree) => tree,
         Err(e) => {
             session
                 .set_flash(FlashMessage::error(format!("Failed to get tag tree: {}", e)))
                 .await
                 .unwrap();
-            return Redirect::to("/manage_tags");
+            return redirect("/manage_tags");
         }
     };
 
@@ -44,7 +46,7 @@ pub async fn route_unset_parent(
 
     // Only try to detach if the tag actually has a parent
     if has_parent_in_tree(&tag_tree, child_id) {
-        if let Err(e) = detach_child_tag(&state.api_addr, child_id).await {
+        if let Err(e) = upstream::call("detach_child_tag", detach_child_tag(&state.api_addr, child_id)).await {
             session
                 .set_flash(FlashMessage::error(format!("Failed to detach tag from current parent: {}", e)))
                 .await
@@ -57,7 +59,7 @@ pub async fn route_unset_parent(
         }
     }
 
-    Redirect::to("/manage_tags")
+    redirect("/manage_tags")
 }
 
 #[derive(Debug, Deserialize)]
@@ -72,7 +74,7 @@ pub async fn route_update_tag(
     Path(id): Path<i32>,
     Form(form): Form<UpdateTagRequest>,
 ) -> Redirect {
-    match update_tag(&state.api_addr, id, form).await {
+    match upstream::call("update_tag", update_tag(&state.api_addr, id, form)).await {
         Ok(_) => {
             session
                 .set_flash(FlashMessage::success("Tag updated successfully"))
@@ -87,7 +89,7 @@ pub async fn route_update_tag(
         }
     }
 
-    Redirect::to("/manage_tags")
+    redirect("/manage_tags")
 }
 
 #[axum::debug_handler]
@@ -98,14 +100,14 @@ pub async fn route_set_parent(
     Form(form): Form<SetParentRequest>,
 ) -> Redirect {
     // Get the full tag tree to check if tag has a parent
-    let tag_tree = match get_tag_tree(&state.api_addr).await {
+    let tag_tree = match upstream::call("get_tag_tree", get_tag_tree(&state.api_addr)).await {
         Ok(tree) => tree,
         Err(e) => {
             session
                 .set_flash(FlashMessage::error(format!("Failed to get tag tree: {}", e)))
                 .await
                 .unwrap();
-            return Redirect::to("/manage_tags");
+            return redirect("/manage_tags");
         }
     };
 
@@ -126,12 +128,12 @@ pub async fn route_set_parent(
 
     // Only try to detach if the tag actually has a parent
     if has_parent_in_tree(&tag_tree, child_id) {
-        if let Err(e) = detach_child_tag(&state.api_addr, child_id).await {
+        if let Err(e) = upstream::call("detach_child_tag", detach_child_tag(&state.api_addr, child_id)).await {
             session
                 .set_flash(FlashMessage::error(format!("Failed to detach tag from current parent: {}", e)))
                 .await
                 .unwrap();
-            return Redirect::to("/manage_tags");
+            return redirect("/manage_tags");
         }
     }
 
@@ -142,7 +144,7 @@ pub async fn route_set_parent(
 
     // If a new parent is specified, attach to it
     if let Some(parent_id) = parent_id {
-        match attach_child_tag(&state.api_addr, parent_id, child_id).await {
+        match upstream::call("attach_child_tag", attach_child_tag(&state.api_addr, parent_id, child_id)).await {
             Ok(_) => {
                 session
                     .set_flash(FlashMessage::success("Tag hierarchy updated successfully"))
@@ -163,5 +165,70 @@ pub async fn route_set_parent(
             .unwrap();
     }
 
-    Redirect::to("/manage_tags")
+    redirect("/manage_tags")
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BulkRenameForm {
+    find: String,
+    #[serde(default)]
+    replace: String,
+}
+
+/// Find and replace over every tag name
+#[axum::debug_handler]
+pub async fn route_bulk_rename_tags(
+    session: Session,
+    State(state): State<AppState>,
+    Form(form): Form<BulkRenameForm>,
+) -> Redirect {
+    if form.find.is_empty() {
+        session
+            .set_flash(FlashMessage::error("Enter the text to find in tag names"))
+            .await
+            .unwrap();
+        return redirect("/manage_tags");
+    }
+
+    let tags = match upstream::call("list_tags", list_tags(&state.api_addr)).await {
+        Ok(tags) => tags,
+        Err(e) => {
+            session
+                .set_flash(FlashMessage::error(format!("Failed to get tags: {}", e)))
+                .await
+                .unwrap();
+            return redirect("/manage_tags");
+        }
+    };
+
+    let mut renamed = 0;
+    let mut errors = Vec::new();
+
+    for tag in tags {
+        if !tag.name.contains(&form.find) {
+            continue;
+        }
+        let name = tag.name.replace(&form.find, &form.replace).trim().to_string();
+        if name.is_empty() {
+            errors.push(format!("'{}' would have an empty name", tag.name));
+            continue;
+        }
+        match upstream::call("update_tag", update_tag(&state.api_addr, tag.id, UpdateTagRequest { name })).await {
+            Ok(_) => renamed += 1,
+            Err(e) => errors.push(format!("'{}': {}", tag.name, e)),
+        }
+    }
+
+    let flash = if errors.is_empty() {
+        FlashMessage::success(format!("Renamed {} tag(s)", renamed))
+    } else {
+        FlashMessage::warning(format!(
+            "Renamed {} tag(s). Problems: {}",
+            renamed,
+            errors.join("; ")
+        ))
+    };
+    session.set_flash(flash).await.unwrap();
+
+    redirect("/manage_tags")
 }
@@ -0,0 +1,86 @@
+use crate::error::AppError;
+use crate::html_builder::{build_children, build_tree, filter_tree, find_node, TreeRenderContext};
+use crate::prefs::PreferenceStore;
+use crate::state::AppState;
+use crate::templates::render_page;
+use crate::upstream;
+use axum::{
+    extract::{Path, Query, State},
+    response::Html,
+};
+use draftsmith_rest_api::client::{fetch_note_tree, NoteTreeNode};
+use minijinja::context;
+use serde::Deserialize;
+use tower_sessions::Session;
+use tracing::warn;
+
+#[derive(Debug, Deserialize)]
+pub struct ChildrenParams {
+    /// The note the page was rendered for, so it stays highlighted
+    pub current: Option<i32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FilterParams {
+    #[serde(default)]
+    pub q: String,
+    pub current: Option<i32>,
+}
+
+/// The note tree, or the snapshot of it while the API is unreachable
+pub(crate) async fn load_tree(state: &AppState) -> Result<Vec<NoteTreeNode>, AppError> {
+    match upstream::call("fetch_note_tree", fetch_note_tree(&state.api_addr)).await {
+        Ok(tree) => {
+            state.snapshot.set_note_tree(&tree);
+            Ok(tree)
+        }
+        Err(e) => match state.snapshot.note_tree() {
+            Some(tree) => {
+                warn!(error = ?e, "Failed to get note tree, using the snapshot");
+                Ok(tree)
+            }
+            None => Err(AppError::upstream("Unable to load the note tree", e)),
+        },
+    }
+}
+
+/// The children of a note as sidebar `<li>` items, fetched when a
+/// collapsed `<details>` in the tree is expanded
+pub async fn route_tree_children(
+    session: Session,
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+    Query(params): Query<ChildrenParams>,
+) -> Result<Html<String>, AppError> {
+    let tree = load_tree(&state).await?;
+
+    let (node, depth) = find_node(&tree, id)
+        .ok_or_else(|| AppError::not_found(format!("Note #{} does not exist", id)))?;
+    let prefs = session.prefs().await.unwrap_or_default();
+    let render = TreeRenderContext::new(params.current).with_open_ids(prefs.expanded_ids);
+
+    render_page(
+        "tree/children.html",
+        context! { nodes => build_children(node, depth, &render) },
+    )
+}
+
+/// The sidebar tree pruned to notes whose titles match the filter box,
+/// swapped in place of the paged tree while the filter is in use
+pub async fn route_tree_filter(
+    State(state): State<AppState>,
+    Query(params): Query<FilterParams>,
+) -> Result<Html<String>, AppError> {
+    let tree = load_tree(&state).await?;
+
+    let query = params.q.trim();
+    let (filtered, render) = filter_tree(&tree, query, params.current);
+
+    render_page(
+        "tree/filter.html",
+        context! {
+            query => query,
+            nodes => build_tree(&filtered, &render),
+        },
+    )
+}
@@ -7,31 +7,53 @@ use tempfile;
 use chrono::{DateTime, Utc};
 use tower_sessions::Session;
 use crate::flash::{FlashMessage, FlashMessageStore};
-use crate::templates::{self, ENV, handle_template_error};
+use crate::error::AppError;
+use crate::templates::render_page;
 use crate::routes::assets::{route_list_assets, route_delete_asset, route_edit_asset};
 use crate::template_context::{BodyTemplateContext, PaginationParams};
 use draftsmith_rest_api::client::assets::{list_assets, create_asset, update_asset, delete_asset};
 use crate::routes::{
     notes::{
+        bulk::route_bulk_notes,
         create::route_create,
         edit::{route_edit, route_update_note},
         note_move::{route_detach_note_post, route_move_note_get, route_move_note_post},
-        view::route_note,
+        view::{route_note, ViewParams},
         delete::route_delete,
+        pin::{route_pin_note, route_reorder_pins, route_unpin_note},
         tags::{route_assign_tags_get, route_assign_tags_post},
     },
     tags::{
         manage_all_tags::route_manage_tags,
         create::route_create_tag,
         delete::route_delete_tag,
-        update::{route_update_tag, route_set_parent, route_unset_parent},
+        update::{route_update_tag, route_set_parent, route_unset_parent, route_bulk_rename_tags},
         list::route_list_tag,
+        merge::route_merge_tags,
+        style::route_set_tag_style,
+        suggest::route_suggest_tags,
+        query::route_query_tags,
     },
+    health::{route_healthz, route_readyz},
+    history::{route_clear_history, route_history, route_history_back, route_history_forward},
+    note_index::route_note_index,
+    prefs::{route_preferences, route_set_timezone, route_set_tree_expanded, route_set_tree_page_size},
     recent::route_recent,
     search::search,
+    tree::{route_tree_children, route_tree_filter},
 };
+use crate::auth::require_basic_auth;
+use crate::config::{api_endpoint, Config};
 use crate::state::AppState;
 use crate::static_files::build_static_routes;
+use crate::snapshot::Snapshot;
+use crate::store::JsonStore;
+use crate::logging;
+use crate::metrics::{self, MeteredStore};
+use tower_http::trace::TraceLayer;
+use crate::tls;
+use crate::urls::{self, url_for};
+use std::sync::Arc;
 use axum::{
     extract::{Path, DefaultBodyLimit, State, Multipart, Query},
     routing::{get, post},
@@ -39,34 +61,99 @@ use axum::{
     Router,
 };
 use tower_http::compression::CompressionLayer;
-use tower_sessions::{MemoryStore, SessionManagerLayer};
+use axum::middleware;
+use tower_sessions::cookie::time::Duration;
+use tower_sessions::{Expiry, MemoryStore, SessionManagerLayer};
+use crate::upstream;
+use tracing::{error, warn, info};
+
+/// How long in-flight TLS connections get to finish after a shutdown signal
+const SHUTDOWN_GRACE_PERIOD: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// Resolve on Ctrl-C or SIGTERM, so requests in flight can finish
+/// before the process exits
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            warn!(error = %e, "Unable to listen for Ctrl-C");
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut stream) => {
+                stream.recv().await;
+            }
+            Err(e) => {
+                warn!(error = %e, "Unable to listen for SIGTERM");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+    info!("Shutting down, waiting for open requests to finish");
+}
 
 #[tokio::main]
-pub async fn serve(api_scheme: &str, api_host: &str, api_port: &u16, host: &str, port: &str) {
-    let api_addr = format!("{api_scheme}://{api_host}:{api_port}");
-    let addr = format!("{}:{}", host, port);
+pub async fn serve(config: Config) {
+    let api_addr = config.api_addr();
+    let addr = config.bind_addr();
 
     let listener = tokio::net::TcpListener::bind(&addr)
         .await
         .expect("Failed to bind address");
 
     // Create session store
-    let session_store = MemoryStore::default();
-    let session_layer = SessionManagerLayer::new(session_store).with_secure(false);
+    let session_store = MeteredStore::new(MemoryStore::default());
+    let mut session_layer =
+        SessionManagerLayer::new(session_store).with_secure(config.session.secure);
+    if let Some(minutes) = config.session.inactivity_minutes {
+        session_layer = session_layer.with_expiry(Expiry::OnInactivity(Duration::minutes(minutes)));
+    }
+
+    // Open the stores for data the API doesn't keep
+    let data_dir = &config.server.data_dir;
+    let tag_styles = JsonStore::open(data_dir, "tag_styles.json")
+        .unwrap_or_else(|e| panic!("Unable to open tag style store. Error: {:#}", e));
+    let pins = JsonStore::open(data_dir, "pins.json")
+        .unwrap_or_else(|e| panic!("Unable to open pin store. Error: {:#}", e));
+
+    let max_body_size = config.limits.max_body_bytes;
+    let tls = config.tls.clone();
+    let https_port = config.server.port;
+    let host = config.server.host.clone();
+
+    let metrics_handle = metrics::install();
+
+    // Templates and redirects read the prefix through `urls::url_for`
+    urls::set_base_path(&config.server.base_path);
+    crate::error::set_debug(config.server.debug);
 
     // Create shared state
     let state = AppState {
         api_addr: api_addr.clone(),
+        config: Arc::new(config),
+        tag_styles: Arc::new(tag_styles),
+        pins: Arc::new(pins),
+        snapshot: Arc::new(Snapshot::new()),
     };
 
-
-    let max_body_size = 1024 * 1024 * 1024; // 1 GB
     // Set up Routes
     let app = Router::<AppState>::new()
         .route(
             "/",
             get(|session, query, state: State<AppState>| {
-                route_note(session, state, Path(1), query)
+                route_note(session, state, Path(1), query, Query(ViewParams::default()))
             }),
         )
         .route("/create", get(|session, state: State<AppState>, query| {
@@ -79,12 +166,22 @@ pub async fn serve(api_scheme: &str, api_host: &str, api_port: &u16, host: &str,
         .nest("/static", build_static_routes())
         .route("/search", get(search))
         .route("/recent", get(route_recent))
+        .route("/history", get(route_history))
+        .route("/history/back", get(route_history_back))
+        .route("/history/forward", get(route_history_forward))
+        .route("/history/clear", post(route_clear_history))
+        .route("/api/index.json", get(route_note_index))
+        .route("/api/tags/suggest", get(route_suggest_tags))
         .route("/manage_tags", get(route_manage_tags))
         .route("/create_tag", post(route_create_tag))
         .route("/delete_tag/:id", post(route_delete_tag))
         .route("/rename_tag/:id", post(route_update_tag))
         .route("/tag/:id/set_parent", post(route_set_parent))
         .route("/tag/:id/unset_parent", post(route_unset_parent))
+        .route("/tag/:id/style", post(route_set_tag_style))
+        .route("/merge_tags", post(route_merge_tags))
+        .route("/bulk_rename_tags", post(route_bulk_rename_tags))
+        .route("/tags", get(route_query_tags))
         .route("/tags/:id", get(route_list_tag))
 
         .route("/note/:id", get(route_note))
@@ -94,6 +191,16 @@ pub async fn serve(api_scheme: &str, api_host: &str, api_port: &u16, host: &str,
             get(route_move_note_get).post(route_move_note_post),
         )
         .route("/note/:id/detach", post(route_detach_note_post))
+        .route("/tree/children/:id", get(route_tree_children))
+        .route("/tree/filter", get(route_tree_filter))
+        .route("/prefs/tree_page_size", post(route_set_tree_page_size))
+        .route("/prefs/tree_expanded", post(route_set_tree_expanded))
+        .route("/prefs/timezone", post(route_set_timezone))
+        .route("/preferences", get(route_preferences))
+        .route("/notes/bulk", post(route_bulk_notes))
+        .route("/note/:id/pin", post(route_pin_note))
+        .route("/note/:id/unpin", post(route_unpin_note))
+        .route("/pins/order", post(route_reorder_pins))
         .route("/assign_tags/:id", get(route_assign_tags_get).post(route_assign_tags_post))
         .route("/m/*file_path", get(route_serve_asset))
         .route("/assets", get(route_list_assets))
@@ -103,11 +210,40 @@ pub async fn serve(api_scheme: &str, api_host: &str, api_port: &u16, host: &str,
             get(|session, state, query| route_upload_asset_form(session, state, query))
             .post(route_upload_asset)
         )
+        .route(
+            "/metrics",
+            get(metrics::route_metrics).with_state(metrics_handle),
+        )
+        .fallback(|| async { AppError::not_found("There is no page at this address") })
         .layer(CompressionLayer::new())
         .layer(DefaultBodyLimit::max(max_body_size))
+        .layer(middleware::from_fn_with_state(state.clone(), require_basic_auth))
+        // Added after the auth layer so supervisors can probe without credentials
+        .route("/healthz", get(route_healthz))
+        .route("/readyz", get(route_readyz))
+        .route_layer(middleware::from_fn(metrics::track_requests))
         .with_state(state)
         .layer(session_layer);
 
+    // Behind a reverse proxy everything lives under the base path,
+    // a request for the bare host is sent there
+    let app = match urls::base_path() {
+        "" => app,
+        base_path => Router::new()
+            .route("/", get(|| async { urls::redirect("/") }))
+            .nest(base_path, app),
+    };
+
+    // The request id is set first, so the span and response both carry it
+    let app = app
+        .layer(logging::propagate_request_id_layer())
+        .layer(
+            TraceLayer::new_for_http()
+                .make_span_with(logging::request_span)
+                .on_response(logging::record_response),
+        )
+        .layer(logging::set_request_id_layer());
+
 async fn route_serve_asset(
     State(state): State<AppState>,
     Path(file_path): Path<String>,
@@ -124,8 +260,19 @@ async fn route_serve_asset(
         }
     };
 
-    let asset_url = format!("{}/assets/download/{}", state.api_addr, file_path);
-    let mut request = client.get(&asset_url);
+    // Join segment by segment so names with spaces etc. are encoded
+    // and a path prefix on the API URL is kept
+    let segments = ["assets", "download"]
+        .into_iter()
+        .chain(file_path.split('/').filter(|s| !s.is_empty()));
+    let asset_url = match api_endpoint(&state.api_addr, segments) {
+        Ok(url) => url,
+        Err(e) => {
+            error!(error = %e, "Failed to build asset URL");
+            return internal_server_error_response();
+        }
+    };
+    let mut request = client.get(asset_url);
 
     // Forward conditional headers if present
     if let Some(if_none_match) = headers.get(IF_NONE_MATCH) {
@@ -175,7 +322,10 @@ async fn route_serve_asset(
 
                     // If API didn't provide cache headers, set reasonable defaults
                     if !headers.contains_key("cache-control") {
-                        builder = builder.header("cache-control", "public, max-age=3600");
+                        builder = builder.header(
+                            "cache-control",
+                            format!("public, max-age={}", state.config.cache.asset_max_age),
+                        );
                     }
                     if !headers.contains_key("etag") && !headers.contains_key("last-modified") {
                         // Generate simple etag from content length and last few bytes
@@ -192,6 +342,7 @@ async fn route_serve_asset(
                         builder = builder.header("etag", simple_etag);
                     }
 
+                    metrics::record_asset_bytes(bytes.len());
                     builder
                         .body(Body::from(bytes))
                         .unwrap_or_else(|_| internal_server_error_response())
@@ -251,27 +402,13 @@ async fn route_upload_asset_form(
     session: Session,
     State(state): State<AppState>,
     Query(params): Query<PaginationParams>,
-) -> Html<String> {
-    let api_addr: String = state.api_addr.clone();
-
+) -> Result<Html<String>, AppError> {
     // Get the body data
-    let body_handler = match BodyTemplateContext::new(session, Query(params), api_addr.clone(), None).await {
-        Ok(handler) => handler,
-        Err(e) => {
-            eprintln!("Failed to create body handler: {:#?}", e);
-            return Html(String::from("<h1>Error getting page data</h1>"));
-        }
-    };
-
-
-    let template = ENV.get_template("body/upload_asset.html").unwrap_or_else(|e| {
-        panic!("Failed to load template. Error: {:#}", e);
-    });
-
-    // Use the context from body_handler
-    let rendered = template.render(body_handler.ctx).unwrap_or_else(handle_template_error);
+    let body_handler = BodyTemplateContext::new(session, Query(params), &state, None)
+        .await
+        .map_err(|e| AppError::upstream("Unable to reach the Draftsmith API", e))?;
 
-    Html(rendered)
+    render_page("body/upload_asset.html", body_handler.ctx)
 }
 
 async fn route_upload_asset(
@@ -309,7 +446,7 @@ async fn route_upload_asset(
             let _ = session.set_flash(FlashMessage::error("No file provided")).await;
             return Response::builder()
                 .status(StatusCode::FOUND)
-                .header("Location", "/upload_asset")
+                .header("Location", url_for("/upload_asset"))
                 .body(Body::empty())
                 .unwrap_or_else(|_| internal_server_error_response());
         }
@@ -322,7 +459,7 @@ async fn route_upload_asset(
             let _ = session.set_flash(FlashMessage::error(&format!("Failed to create temporary file: {}", e))).await;
             return Response::builder()
                 .status(StatusCode::FOUND)
-                .header("Location", "/upload_asset")
+                .header("Location", url_for("/upload_asset"))
                 .body(Body::empty())
                 .unwrap_or_else(|_| internal_server_error_response());
         }
@@ -333,7 +470,7 @@ async fn route_upload_asset(
         let _ = session.set_flash(FlashMessage::error(&format!("Failed to write temporary file: {}", e))).await;
         return Response::builder()
             .status(StatusCode::FOUND)
-            .header("Location", "/upload_asset")
+            .header("Location", url_for("/upload_asset"))
             .body(Body::empty())
             .unwrap_or_else(|_| internal_server_error_response());
     }
@@ -342,13 +479,13 @@ async fn route_upload_asset(
     let final_location = custom_location.or(Some(original_filename));
 
     // Use create_asset function
-    let result = create_asset(
+    let result = upstream::call("create_asset", create_asset(
         &state.api_addr,
         temp_file.path(),
         None, // no note_id
         None, // no description
         final_location, // either custom filename or original filename
-    ).await;
+    )).await;
 
     // The temporary file will be automatically deleted when temp_file is dropped
     // at the end of this function, no manual cleanup needed!
@@ -372,7 +509,7 @@ async fn route_upload_asset(
 
     Response::builder()
         .status(StatusCode::FOUND)
-        .header("Location", "/upload_asset")
+        .header("Location", url_for("/upload_asset"))
         .body(Body::empty())
         .unwrap_or_else(|_| internal_server_error_response())
 }
@@ -396,8 +533,49 @@ fn map_upstream_error(status: StatusCode) -> Response {
 }
 
     // Do it!
-    axum::serve(listener, app)
-        .tcp_nodelay(true)
+    let Some((cert, key)) = tls.files() else {
+        axum::serve(listener, app)
+            .tcp_nodelay(true)
+            .with_graceful_shutdown(shutdown_signal())
+            .await
+            .unwrap_or_else(|e| panic!("Unable to serve application. Error: {:#}", e));
+        return;
+    };
+
+    let rustls_config = tls::load_rustls_config(cert, key).await;
+    tokio::spawn(tls::reload_on_sighup(
+        rustls_config.clone(),
+        cert.clone(),
+        key.clone(),
+    ));
+
+    if let Some(http_port) = tls.redirect_http_port {
+        let http_listener = tokio::net::TcpListener::bind(format!("{}:{}", host, http_port))
+            .await
+            .expect("Failed to bind HTTP redirect address");
+        tokio::spawn(async move {
+            axum::serve(http_listener, tls::https_redirect_app(https_port))
+                .with_graceful_shutdown(shutdown_signal())
+                .await
+                .unwrap_or_else(|e| error!(error = %e, "HTTP redirect listener stopped"));
+        });
+    }
+
+    let handle = axum_server::Handle::new();
+    tokio::spawn({
+        let handle = handle.clone();
+        async move {
+            shutdown_signal().await;
+            handle.graceful_shutdown(Some(SHUTDOWN_GRACE_PERIOD));
+        }
+    });
+
+    let listener = listener
+        .into_std()
+        .expect("Failed to hand the listener over to the TLS server");
+    axum_server::from_tcp_rustls(listener, rustls_config)
+        .handle(handle)
+        .serve(app.into_make_service())
         .await
         .unwrap_or_else(|e| panic!("Unable to serve application. Error: {:#}", e));
 }
@@ -0,0 +1,110 @@
+//! Last-known-good copies of what pages need from the Draftsmith API.
+//!
+//! Every successful fetch refreshes the snapshot. While the API is
+//! unreachable the layout is rendered from it instead, and notes that were
+//! viewed recently can still be read, but not edited.
+//!
+//! The snapshot only lives in memory, a restart during an outage starts
+//! without one and pages render without the sidebar.
+
+use draftsmith_rest_api::client::NoteTreeNode;
+use minijinja::Value;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::time::Instant;
+
+/// How many recently viewed notes are kept for reading during an outage
+const MAX_NOTES: usize = 50;
+
+#[derive(Default)]
+pub struct Snapshot {
+    inner: RwLock<Inner>,
+}
+
+#[derive(Default)]
+struct Inner {
+    note_tree: Option<Vec<NoteTreeNode>>,
+    tag_tree: Option<Value>,
+    /// Most recently viewed first
+    notes: VecDeque<SnapshotNote>,
+    updated_at: Option<Instant>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SnapshotNote {
+    pub id: i32,
+    pub title: String,
+    /// The note as the API returned it
+    pub note: Value,
+    pub rendered_html: Option<String>,
+}
+
+impl Snapshot {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn read(&self) -> RwLockReadGuard<'_, Inner> {
+        self.inner.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn write(&self) -> RwLockWriteGuard<'_, Inner> {
+        let mut inner = self.inner.write().unwrap_or_else(|e| e.into_inner());
+        inner.updated_at = Some(Instant::now());
+        inner
+    }
+
+    pub fn note_tree(&self) -> Option<Vec<NoteTreeNode>> {
+        self.read().note_tree.clone()
+    }
+
+    pub fn set_note_tree(&self, tree: &[NoteTreeNode]) {
+        self.write().note_tree = Some(tree.to_vec());
+    }
+
+    pub fn tag_tree(&self) -> Option<Value> {
+        self.read().tag_tree.clone()
+    }
+
+    pub fn set_tag_tree<T: Serialize>(&self, tree: &T) {
+        self.write().tag_tree = Some(Value::from_serialize(tree));
+    }
+
+    pub fn note(&self, id: i32) -> Option<SnapshotNote> {
+        self.read().notes.iter().find(|n| n.id == id).cloned()
+    }
+
+    /// Recently viewed notes, most recent first
+    pub fn notes(&self) -> Vec<SnapshotNote> {
+        self.read().notes.iter().cloned().collect()
+    }
+
+    /// Remember a viewed note, keeping its rendered HTML if it had some
+    pub fn set_note<T: Serialize>(&self, id: i32, title: &str, note: &T) {
+        let mut inner = self.write();
+        let rendered_html = match inner.notes.iter().position(|n| n.id == id) {
+            Some(pos) => inner.notes.remove(pos).and_then(|n| n.rendered_html),
+            None => None,
+        };
+        inner.notes.push_front(SnapshotNote {
+            id,
+            title: title.to_string(),
+            note: Value::from_serialize(note),
+            rendered_html,
+        });
+        inner.notes.truncate(MAX_NOTES);
+    }
+
+    pub fn set_rendered_html(&self, id: i32, html: &str) {
+        let mut inner = self.write();
+        if let Some(note) = inner.notes.iter_mut().find(|n| n.id == id) {
+            note.rendered_html = Some(html.to_string());
+        }
+    }
+
+    /// Minutes since the API last answered, if it ever did
+    pub fn age_minutes(&self) -> Option<u64> {
+        self.read().updated_at.map(|t| t.elapsed().as_secs() / 60)
+    }
+}
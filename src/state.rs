@@ -1,4 +1,19 @@
+use crate::config::Config;
+use crate::routes::notes::pin::Pins;
+use crate::routes::tags::style::TagStyles;
+use crate::snapshot::Snapshot;
+use crate::store::JsonStore;
+use std::sync::Arc;
+
 #[derive(Clone)]
 pub struct AppState {
     pub api_addr: String,
+    /// The effective configuration `serve` was started with
+    pub config: Arc<Config>,
+    /// Colors and icons for tags, keyed by tag id
+    pub tag_styles: Arc<JsonStore<TagStyles>>,
+    /// Notes pinned above the sidebar tree, in display order
+    pub pins: Arc<JsonStore<Pins>>,
+    /// Last-known-good API data, served while the API is unreachable
+    pub snapshot: Arc<Snapshot>,
 }
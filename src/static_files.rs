@@ -1,7 +1,7 @@
 use crate::state::AppState;
 use axum::http::StatusCode;
 use axum::{
-    extract::Path,
+    extract::{Path, State},
     http::header::{self, CACHE_CONTROL, CONTENT_TYPE},
     response::IntoResponse,
     routing::get,
@@ -95,8 +95,9 @@ async fn get_static_katex_fonts(Path(path): Path<String>) -> impl IntoResponse {
     )
 }
 
-async fn get_static_file(dir: &Dir<'_>, path: String) -> impl IntoResponse {
+async fn get_static_file(dir: &Dir<'_>, path: String, max_age: u64) -> impl IntoResponse {
     if let Some(file) = dir.get_file(&path) {
+        let cache_control = format!("public, max-age={}, immutable", max_age);
         let content_type = determine_content_type(file.path());
         let contents = file.contents();
 
@@ -113,7 +114,7 @@ async fn get_static_file(dir: &Dir<'_>, path: String) -> impl IntoResponse {
                 StatusCode::OK,
                 [
                     (CONTENT_TYPE, content_type),
-                    (CACHE_CONTROL, "public, max-age=604800, immutable".to_string()),
+                    (CACHE_CONTROL, cache_control),
                     (header::CONTENT_ENCODING, "gzip".to_string()),
                 ],
                 compressed,
@@ -124,7 +125,7 @@ async fn get_static_file(dir: &Dir<'_>, path: String) -> impl IntoResponse {
             StatusCode::OK,
             [
                 (CONTENT_TYPE, content_type),
-                (CACHE_CONTROL, "public, max-age=604800, immutable".to_string()),
+                (CACHE_CONTROL, cache_control),
                 (CONTENT_TYPE, "".to_string()), // Added to match array size
             ],
             contents.to_vec(),
@@ -150,22 +151,37 @@ fn determine_content_type(path: &std::path::Path) -> String {
         .to_string()
 }
 
-async fn get_static_js(Path(path): Path<String>) -> impl IntoResponse {
-    get_static_file(&JS_DIR, path).await
+async fn get_static_js(
+    State(state): State<AppState>,
+    Path(path): Path<String>,
+) -> impl IntoResponse {
+    get_static_file(&JS_DIR, path, state.config.cache.static_max_age).await
 }
 
-async fn get_stimulus_js(Path(path): Path<String>) -> impl IntoResponse {
-    get_static_file(&STIMULUS_DIR, path).await
+async fn get_stimulus_js(
+    State(state): State<AppState>,
+    Path(path): Path<String>,
+) -> impl IntoResponse {
+    get_static_file(&STIMULUS_DIR, path, state.config.cache.static_max_age).await
 }
 
-async fn get_static_media(Path(path): Path<String>) -> impl IntoResponse {
-    get_static_file(&MEDIA_DIR, path).await
+async fn get_static_media(
+    State(state): State<AppState>,
+    Path(path): Path<String>,
+) -> impl IntoResponse {
+    get_static_file(&MEDIA_DIR, path, state.config.cache.static_max_age).await
 }
 
-async fn get_static_css(Path(path): Path<String>) -> impl IntoResponse {
-    get_static_file(&CSS_DIR, path).await
+async fn get_static_css(
+    State(state): State<AppState>,
+    Path(path): Path<String>,
+) -> impl IntoResponse {
+    get_static_file(&CSS_DIR, path, state.config.cache.static_max_age).await
 }
 
-async fn get_controllers(Path(path): Path<String>) -> impl IntoResponse {
-    get_static_file(&CONTROLLERS_DIR, path).await
+async fn get_controllers(
+    State(state): State<AppState>,
+    Path(path): Path<String>,
+) -> impl IntoResponse {
+    get_static_file(&CONTROLLERS_DIR, path, state.config.cache.static_max_age).await
 }
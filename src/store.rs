@@ -0,0 +1,117 @@
+use serde::{de::DeserializeOwned, Serialize};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use tokio::sync::Mutex;
+
+/// Small persistent store for data the Draftsmith API has no field for.
+///
+/// The whole value lives in memory and is rewritten to a JSON file in the
+/// data directory on every update, which is fine for the handful of
+/// preferences the web app keeps.
+pub struct JsonStore<T> {
+    path: PathBuf,
+    data: RwLock<T>,
+    /// Held while writing the file, so writes land in the order of the updates
+    writing: Mutex<()>,
+}
+
+impl<T> JsonStore<T>
+where
+    T: Serialize + DeserializeOwned + Default + Clone,
+{
+    /// Load `file_name` from `data_dir`, starting empty if it doesn't exist yet
+    pub fn open(data_dir: &Path, file_name: &str) -> io::Result<Self> {
+        std::fs::create_dir_all(data_dir)?;
+        let path = data_dir.join(file_name);
+
+        let data = match std::fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str(&contents)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => T::default(),
+            Err(e) => return Err(e),
+        };
+
+        Ok(Self {
+            path,
+            data: RwLock::new(data),
+            writing: Mutex::new(()),
+        })
+    }
+
+    /// A snapshot of the current value
+    pub fn get(&self) -> T {
+        match self.data.read() {
+            Ok(data) => data.clone(),
+            Err(poisoned) => poisoned.into_inner().clone(),
+        }
+    }
+
+    /// Modify the value and write it back to disk.
+    ///
+    /// `f` changes a copy, which only replaces the in-memory value once it's
+    /// on disk, so a failed write leaves both as they were. The file is
+    /// written on the blocking pool.
+    pub async fn update<R>(&self, f: impl FnOnce(&mut T) -> R) -> io::Result<R> {
+        // Updates are applied one at a time, so none works on a stale copy
+        let _writing = self.writing.lock().await;
+        let mut updated = self.get();
+        let result = f(&mut updated);
+        let contents = serde_json::to_vec_pretty(&updated)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+        // Write to a sibling file and rename so a crash can't truncate the store
+        let path = self.path.clone();
+        tokio::task::spawn_blocking(move || {
+            let tmp_path = path.with_extension("json.tmp");
+            std::fs::write(&tmp_path, contents)?;
+            std::fs::rename(&tmp_path, &path)
+        })
+        .await
+        .map_err(io::Error::other)??;
+
+        match self.data.write() {
+            Ok(mut data) => *data = updated,
+            Err(poisoned) => *poisoned.into_inner() = updated,
+        }
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn failed_writes_leave_the_value_unchanged() {
+        let dir = tempfile::tempdir().unwrap();
+        let store: JsonStore<Vec<i32>> = JsonStore::open(dir.path(), "pins.json").unwrap();
+        store.update(|pins| pins.push(1)).await.unwrap();
+
+        // A directory where the temporary file goes makes the write fail,
+        // unlike permissions this also holds when the tests run as root
+        std::fs::create_dir(dir.path().join("pins.json.tmp")).unwrap();
+        assert!(store.update(|pins| pins.push(2)).await.is_err());
+
+        assert_eq!(store.get(), vec![1]);
+        let reopened: JsonStore<Vec<i32>> = JsonStore::open(dir.path(), "pins.json").unwrap();
+        assert_eq!(reopened.get(), vec![1]);
+    }
+
+    #[tokio::test]
+    async fn updates_are_persisted() {
+        let dir = tempfile::tempdir().unwrap();
+        let store: JsonStore<Vec<i32>> = JsonStore::open(dir.path(), "pins.json").unwrap();
+        let len = store
+            .update(|pins| {
+                pins.extend([3, 1]);
+                pins.len()
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(len, 2);
+        let reopened: JsonStore<Vec<i32>> = JsonStore::open(dir.path(), "pins.json").unwrap();
+        assert_eq!(reopened.get(), vec![3, 1]);
+    }
+}
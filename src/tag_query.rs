@@ -0,0 +1,384 @@
+//! Boolean tag queries, e.g. `project AND (urgent OR blocked) NOT archived`
+//!
+//! - Keywords (`AND`, `OR`, `NOT`) are case-insensitive
+//! - Terms next to each other are joined with `AND`, so `a NOT b` is `a AND NOT b`
+//! - Tag names containing spaces or parentheses must be quoted: `"to read"`
+//! - A trailing `*` on a term also matches the tag's descendants: `project*`
+
+use draftsmith_rest_api::client::tags::TagTreeNode;
+use std::collections::HashSet;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TagQuery {
+    Tag { name: String, descendants: bool },
+    And(Box<TagQuery>, Box<TagQuery>),
+    Or(Box<TagQuery>, Box<TagQuery>),
+    Not(Box<TagQuery>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    LParen,
+    RParen,
+    And,
+    Or,
+    Not,
+    Term { name: String, descendants: bool },
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' => {
+                chars.next();
+                tokens.push(Token::LParen);
+            }
+            ')' => {
+                chars.next();
+                tokens.push(Token::RParen);
+            }
+            '"' => {
+                chars.next();
+                let mut name = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some(c) => name.push(c),
+                        None => return Err(String::from("Unterminated quote in tag query")),
+                    }
+                }
+                let descendants = chars.next_if_eq(&'*').is_some();
+                tokens.push(Token::Term { name, descendants });
+            }
+            _ => {
+                let mut word = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || c == '(' || c == ')' || c == '"' {
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+
+                let token = match word.to_uppercase().as_str() {
+                    "AND" => Token::And,
+                    "OR" => Token::Or,
+                    "NOT" => Token::Not,
+                    _ => match word.strip_suffix('*') {
+                        Some(name) => Token::Term {
+                            name: name.to_string(),
+                            descendants: true,
+                        },
+                        None => Token::Term {
+                            name: word,
+                            descendants: false,
+                        },
+                    },
+                };
+                tokens.push(token);
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    // or_expr := and_expr (OR and_expr)*
+    fn parse_or(&mut self) -> Result<TagQuery, String> {
+        let mut lhs = self.parse_and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.next();
+            let rhs = self.parse_and()?;
+            lhs = TagQuery::Or(Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    // and_expr := unary ((AND)? unary)*
+    fn parse_and(&mut self) -> Result<TagQuery, String> {
+        let mut lhs = self.parse_unary()?;
+        loop {
+            match self.peek() {
+                Some(Token::And) => {
+                    self.next();
+                }
+                Some(Token::Not) | Some(Token::LParen) | Some(Token::Term { .. }) => {}
+                _ => break,
+            }
+            let rhs = self.parse_unary()?;
+            lhs = TagQuery::And(Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    // unary := NOT unary | '(' or_expr ')' | term
+    fn parse_unary(&mut self) -> Result<TagQuery, String> {
+        match self.next() {
+            Some(Token::Not) => Ok(TagQuery::Not(Box::new(self.parse_unary()?))),
+            Some(Token::LParen) => {
+                let inner = self.parse_or()?;
+                match self.next() {
+                    Some(Token::RParen) => Ok(inner),
+                    _ => Err(String::from("Missing closing parenthesis in tag query")),
+                }
+            }
+            Some(Token::Term { name, descendants }) => Ok(TagQuery::Tag { name, descendants }),
+            Some(Token::RParen) => Err(String::from("Unexpected ')' in tag query")),
+            Some(Token::And) | Some(Token::Or) => {
+                Err(String::from("Expected a tag name before AND/OR"))
+            }
+            None => Err(String::from("Tag query ended unexpectedly")),
+        }
+    }
+}
+
+impl TagQuery {
+    pub fn parse(input: &str) -> Result<Self, String> {
+        let tokens = tokenize(input)?;
+        if tokens.is_empty() {
+            return Err(String::from("Tag query is empty"));
+        }
+
+        let mut parser = Parser { tokens, pos: 0 };
+        let query = parser.parse_or()?;
+        if parser.pos < parser.tokens.len() {
+            return Err(String::from("Unexpected ')' in tag query"));
+        }
+        Ok(query)
+    }
+
+    /// Resolve tag names against the tag tree.
+    ///
+    /// Names are matched case-insensitively; if several tags share a name,
+    /// a term matches any of them. `all_descendants` applies `*` to every term.
+    pub fn resolve(
+        &self,
+        tag_tree: &[TagTreeNode],
+        all_descendants: bool,
+    ) -> Result<ResolvedTagQuery, String> {
+        Ok(match self {
+            TagQuery::Tag { name, descendants } => {
+                let mut ids = HashSet::new();
+                collect_matching_tags(
+                    tag_tree,
+                    name,
+                    *descendants || all_descendants,
+                    false,
+                    &mut ids,
+                );
+                if ids.is_empty() {
+                    return Err(format!("Unknown tag: {}", name));
+                }
+                ResolvedTagQuery::Tags(ids)
+            }
+            TagQuery::And(lhs, rhs) => ResolvedTagQuery::And(
+                Box::new(lhs.resolve(tag_tree, all_descendants)?),
+                Box::new(rhs.resolve(tag_tree, all_descendants)?),
+            ),
+            TagQuery::Or(lhs, rhs) => ResolvedTagQuery::Or(
+                Box::new(lhs.resolve(tag_tree, all_descendants)?),
+                Box::new(rhs.resolve(tag_tree, all_descendants)?),
+            ),
+            TagQuery::Not(inner) => {
+                ResolvedTagQuery::Not(Box::new(inner.resolve(tag_tree, all_descendants)?))
+            }
+        })
+    }
+}
+
+/// Walk the tag tree collecting ids of tags named `name`.
+/// `inside_match` is set while walking below a matching tag whose
+/// descendants should be included.
+fn collect_matching_tags(
+    nodes: &[TagTreeNode],
+    name: &str,
+    descendants: bool,
+    inside_match: bool,
+    ids: &mut HashSet<i32>,
+) {
+    for node in nodes {
+        let is_match = node.name.eq_ignore_ascii_case(name);
+        if is_match || inside_match {
+            ids.insert(node.id);
+        }
+        collect_matching_tags(
+            &node.children,
+            name,
+            descendants,
+            inside_match || (is_match && descendants),
+            ids,
+        );
+    }
+}
+
+/// A [`TagQuery`] with each term resolved to the set of tag ids it matches
+#[derive(Debug, Clone)]
+pub enum ResolvedTagQuery {
+    Tags(HashSet<i32>),
+    And(Box<ResolvedTagQuery>, Box<ResolvedTagQuery>),
+    Or(Box<ResolvedTagQuery>, Box<ResolvedTagQuery>),
+    Not(Box<ResolvedTagQuery>),
+}
+
+impl ResolvedTagQuery {
+    /// Does a note carrying `note_tags` satisfy the query?
+    pub fn matches(&self, note_tags: &HashSet<i32>) -> bool {
+        match self {
+            ResolvedTagQuery::Tags(ids) => !ids.is_disjoint(note_tags),
+            ResolvedTagQuery::And(lhs, rhs) => lhs.matches(note_tags) && rhs.matches(note_tags),
+            ResolvedTagQuery::Or(lhs, rhs) => lhs.matches(note_tags) || rhs.matches(note_tags),
+            ResolvedTagQuery::Not(inner) => !inner.matches(note_tags),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tag(name: &str) -> TagQuery {
+        TagQuery::Tag {
+            name: name.to_string(),
+            descendants: false,
+        }
+    }
+
+    fn and(lhs: TagQuery, rhs: TagQuery) -> TagQuery {
+        TagQuery::And(Box::new(lhs), Box::new(rhs))
+    }
+
+    fn or(lhs: TagQuery, rhs: TagQuery) -> TagQuery {
+        TagQuery::Or(Box::new(lhs), Box::new(rhs))
+    }
+
+    fn not(inner: TagQuery) -> TagQuery {
+        TagQuery::Not(Box::new(inner))
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        assert_eq!(
+            TagQuery::parse("a OR b AND c").unwrap(),
+            or(tag("a"), and(tag("b"), tag("c")))
+        );
+        assert_eq!(
+            TagQuery::parse("a AND b OR c").unwrap(),
+            or(and(tag("a"), tag("b")), tag("c"))
+        );
+    }
+
+    #[test]
+    fn not_binds_tighter_than_and() {
+        assert_eq!(
+            TagQuery::parse("NOT a AND b").unwrap(),
+            and(not(tag("a")), tag("b"))
+        );
+        assert_eq!(
+            TagQuery::parse("a NOT b").unwrap(),
+            and(tag("a"), not(tag("b")))
+        );
+    }
+
+    #[test]
+    fn parentheses_override_precedence() {
+        assert_eq!(
+            TagQuery::parse("project AND (urgent OR blocked) NOT archived").unwrap(),
+            and(
+                and(tag("project"), or(tag("urgent"), tag("blocked"))),
+                not(tag("archived"))
+            )
+        );
+    }
+
+    #[test]
+    fn keywords_are_case_insensitive() {
+        assert_eq!(
+            TagQuery::parse("a or not b").unwrap(),
+            or(tag("a"), not(tag("b")))
+        );
+    }
+
+    #[test]
+    fn quoted_names_and_descendants() {
+        assert_eq!(
+            TagQuery::parse(r#""to read" project*"#).unwrap(),
+            and(
+                tag("to read"),
+                TagQuery::Tag {
+                    name: String::from("project"),
+                    descendants: true,
+                }
+            )
+        );
+        assert_eq!(
+            TagQuery::parse(r#""(draft) or not"*"#).unwrap(),
+            TagQuery::Tag {
+                name: String::from("(draft) or not"),
+                descendants: true,
+            }
+        );
+    }
+
+    #[test]
+    fn unbalanced_parentheses_are_rejected() {
+        assert!(TagQuery::parse("(a OR b").is_err());
+        assert!(TagQuery::parse("a OR b)").is_err());
+        assert!(TagQuery::parse("(a))").is_err());
+        assert!(TagQuery::parse(")a(").is_err());
+        assert!(TagQuery::parse("()").is_err());
+    }
+
+    #[test]
+    fn incomplete_queries_are_rejected() {
+        assert!(TagQuery::parse("").is_err());
+        assert!(TagQuery::parse("   ").is_err());
+        assert!(TagQuery::parse("a AND").is_err());
+        assert!(TagQuery::parse("OR b").is_err());
+        assert!(TagQuery::parse("NOT").is_err());
+        assert!(TagQuery::parse(r#""unterminated"#).is_err());
+    }
+
+    #[test]
+    fn resolved_queries_match_note_tags() {
+        let ids = |ids: &[i32]| ids.iter().copied().collect::<HashSet<i32>>();
+        // 1 AND (2 OR 3) AND NOT 4
+        let query = ResolvedTagQuery::And(
+            Box::new(ResolvedTagQuery::And(
+                Box::new(ResolvedTagQuery::Tags(ids(&[1]))),
+                Box::new(ResolvedTagQuery::Or(
+                    Box::new(ResolvedTagQuery::Tags(ids(&[2]))),
+                    Box::new(ResolvedTagQuery::Tags(ids(&[3]))),
+                )),
+            )),
+            Box::new(ResolvedTagQuery::Not(Box::new(ResolvedTagQuery::Tags(ids(&[4]))))),
+        );
+
+        assert!(query.matches(&ids(&[1, 2])));
+        assert!(query.matches(&ids(&[1, 3, 5])));
+        assert!(!query.matches(&ids(&[1])));
+        assert!(!query.matches(&ids(&[1, 2, 4])));
+        assert!(!query.matches(&ids(&[2, 3])));
+    }
+}
@@ -1,6 +1,7 @@
 use crate::flash::FlashMessageStore;
-use crate::html_builder::build_note_tree_html;
-use crate::MAX_ITEMS_PER_PAGE;
+use crate::html_builder::{build_tree_pages, find_node, page_for_note, paginate_tree, TreeRenderContext};
+use crate::prefs::{PreferenceStore, TREE_PAGE_SIZES};
+use crate::state::AppState;
 use axum::extract::Query;
 use minijinja::Environment;
 use std::collections::HashSet;
@@ -10,25 +11,19 @@ use draftsmith_rest_api::client::{
     fetch_note, fetch_note_tree, get_note_breadcrumbs,
     notes::{get_note_rendered_html, NoteError},
 };
-use minijinja::context;
+use minijinja::{context, Value};
 use serde::Deserialize;
 use tower_sessions::Session;
+use crate::error::is_not_found;
+use crate::snapshot::Snapshot;
+use crate::upstream;
+use std::sync::Arc;
+use tracing::{error, warn};
 
 
 #[derive(Debug, Deserialize)]
 pub struct PaginationParams {
-    pub page: Option<i32>,
-}
-
-fn find_page_for_note(tree_pages: &[String], note_id: Option<i32>) -> i32 {
-    if let Some(note_id) = note_id {
-        for (index, page) in tree_pages.iter().enumerate() {
-            if page.contains(&format!("data-note-id=\"{}\"", note_id)) {
-                return (index + 1) as i32;
-            }
-        }
-    }
-    1 // Default to first page if note not found
+    pub page: Option<usize>,
 }
 
 /*
@@ -51,8 +46,25 @@ fn find_page_for_note(tree_pages: &[String], note_id: Option<i32>) -> i32 {
                 - body/note/move.html
 */
 
+/// What `degraded_banner.html` needs when a page is served from the snapshot
+fn snapshot_status(snapshot: &Snapshot, unavailable: bool) -> Value {
+    context! {
+        api_unavailable => unavailable,
+        snapshot_age => if unavailable { snapshot.age_minutes() } else { None },
+        snapshot_notes => if unavailable { snapshot.notes() } else { Vec::new() },
+    }
+}
+
+/// The first of merged contexts wins, so the note's own snapshot status
+/// goes before the layout's, which only knows about the tree
+fn note_page_context(layout: Value, snapshot: &Snapshot, read_only: bool, note: Value) -> Value {
+    context! { ..snapshot_status(snapshot, read_only), ..note, ..layout }
+}
+
 #[derive(Clone)]
 pub struct BodyTemplateContext {
+    /// The note tree came from the snapshot, so the API is likely unreachable
+    pub degraded: bool,
     pub ctx: minijinja::Value,
 }
 
@@ -60,13 +72,43 @@ impl BodyTemplateContext {
     pub async fn new(
         session: Session,
         Query(params): Query<PaginationParams>,
-        api_addr: String,
+        state: &AppState,
         id: Option<i32>,
     ) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
-        // Get tree
-        let tree_pages = fetch_note_tree(&api_addr).await?;
-        let tree_html =
-            build_note_tree_html(tree_pages.clone(), id, Vec::new(), MAX_ITEMS_PER_PAGE);
+        let api_addr = &state.api_addr;
+
+        // Get tree, falling back to the last one seen if the API is down
+        let mut degraded = false;
+        let tree_pages = match upstream::call("fetch_note_tree", fetch_note_tree(api_addr)).await {
+            Ok(tree) => {
+                state.snapshot.set_note_tree(&tree);
+                tree
+            }
+            Err(e) => {
+                warn!(error = ?e, "Failed to get note tree, using the snapshot");
+                degraded = true;
+                state.snapshot.note_tree().unwrap_or_default()
+            }
+        };
+        // Page the tree by its structure, with the user's page size
+        let prefs = session.prefs().await.unwrap_or_default();
+        let page_size = prefs.tree_page_size();
+        let render = TreeRenderContext::new(id).with_open_ids(prefs.expanded_ids);
+        let page_ranges = paginate_tree(&tree_pages, &render, page_size);
+        let tree_views = build_tree_pages(&tree_pages, &page_ranges, &render);
+
+        // Pinned notes, titles come from the tree so deleted notes drop out
+        let pinned_ids = state.pins.get();
+        let pinned: Vec<_> = pinned_ids
+            .iter()
+            .filter_map(|&pin| find_node(&tree_pages, pin))
+            .map(|(node, _)| {
+                context! {
+                    id => node.id,
+                    title => node.title.as_deref().unwrap_or("Untitled"),
+                }
+            })
+            .collect();
 
         // Get any Flash
         let flash = session.take_flash().await.unwrap_or(None);
@@ -74,7 +116,9 @@ impl BodyTemplateContext {
         // Get sidebar page number from query params if present, otherwise find the page containing the note
         let current_page = params
             .page
-            .unwrap_or_else(|| find_page_for_note(&tree_html, id));
+            .or_else(|| id.and_then(|id| page_for_note(&tree_pages, &page_ranges, id)))
+            .unwrap_or(1)
+            .clamp(1, tree_views.len().max(1));
 
         // Store current page in session
         // TODO don't panic
@@ -83,22 +127,36 @@ impl BodyTemplateContext {
             .await
             .expect("Unable to store current page");
 
-        // Get the tag tree
-        let tag_tree = match get_tag_tree(&api_addr).await {
-            Ok(tree) => tree,
+        // Get the tag tree. Only the sidebar uses it, so a failure here
+        // doesn't make the page read-only like a missing note tree does.
+        let tag_tree = match upstream::call("get_tag_tree", get_tag_tree(api_addr)).await {
+            Ok(tree) => {
+                state.snapshot.set_tag_tree(&tree);
+                Value::from_serialize(&tree)
+            }
             Err(e) => {
-                eprintln!("Failed to get tag tree: {:#?}", e);
-                Vec::new()
+                error!(error = ?e, "Failed to get tag tree");
+                state
+                    .snapshot
+                    .tag_tree()
+                    .unwrap_or_else(|| Value::from(Vec::<Value>::new()))
             }
         };
 
         Ok(Self {
+            degraded,
             ctx: context!(
-            tree => tree_html,
-            pages => tree_pages,
+            tree => tree_views,
+            pinned => pinned,
+            pinned_ids => pinned_ids,
+            tree_page_size => page_size,
+            display_timezone => prefs.display_timezone(state.config.server.timezone()).name(),
+            tree_page_sizes => TREE_PAGE_SIZES,
             flash => flash,
             current_page => current_page,
             tag_tree => tag_tree,
+            tag_styles => state.tag_styles.get(),
+            ..snapshot_status(&state.snapshot, degraded)
                 ),
         })
     }
@@ -107,6 +165,10 @@ impl BodyTemplateContext {
 #[derive(Clone)]
 pub struct NoteTemplateContext {
     api_addr: String,
+    snapshot: Arc<Snapshot>,
+    pub title: String,
+    /// Shown from the snapshot, nothing can be changed until the API is back
+    pub read_only: bool,
     pub ctx: minijinja::Value,
 }
 
@@ -114,46 +176,48 @@ impl NoteTemplateContext {
     pub async fn new(
         session: Session,
         Query(params): Query<PaginationParams>,
-        api_addr: String,
+        state: &AppState,
         note_id: i32,
     ) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let api_addr = state.api_addr.clone();
+
         // Get body handler data
         let body_handler =
-            BodyTemplateContext::new(session, Query(params), api_addr.clone(), Some(note_id))
+            BodyTemplateContext::new(session, Query(params), state, Some(note_id))
                 .await?;
 
         // Get breadcrumbs
-        let breadcrumbs = match get_note_breadcrumbs(&api_addr, note_id).await {
+        let breadcrumbs = match upstream::call("get_note_breadcrumbs", get_note_breadcrumbs(&api_addr, note_id)).await {
             Ok(b) => b,
             Err(e) => {
-                eprintln!("Failed to get Note Breadcrumbs: {:#?}", e);
+                error!(error = ?e, "Failed to get Note Breadcrumbs");
                 Vec::new()
             }
         };
 
         // Get the backlinks
-        let backlinks = match get_backlinks(&api_addr, note_id).await {
+        let backlinks = match upstream::call("get_backlinks", get_backlinks(&api_addr, note_id)).await {
             Ok(b) => b,
             Err(e) => {
-                eprintln!("Failed to get backlinks: {:#?}", e);
+                error!(error = ?e, "Failed to get backlinks");
                 Vec::new()
             }
         };
 
         // Get the forward links
-        let forward_links = match get_forward_links(&api_addr, note_id).await {
+        let forward_links = match upstream::call("get_forward_links", get_forward_links(&api_addr, note_id)).await {
             Ok(b) => b,
             Err(e) => {
-                eprintln!("Failed to get forward links: {:#?}", e);
+                error!(error = ?e, "Failed to get forward links");
                 Vec::new()
             }
         };
 
         // Get the tags
-        let note_tag_relations = match list_note_tags(&api_addr).await {
+        let note_tag_relations = match upstream::call("list_note_tags", list_note_tags(&api_addr)).await {
             Ok(t) => t,
             Err(e) => {
-                eprintln!("Failed to get note-tag relations: {:#?}", e);
+                error!(error = ?e, "Failed to get note-tag relations");
                 Vec::new()
             }
         };
@@ -166,7 +230,7 @@ impl NoteTemplateContext {
         // Resolve tag IDs to actual tag objects
         let mut tags = Vec::new();
         for note_tag in note_tags {
-            if let Ok(tag) = get_tag(&api_addr, note_tag.tag_id).await {
+            if let Ok(tag) = upstream::call("get_tag", get_tag(&api_addr, note_tag.tag_id)).await {
                 tags.push(tag);
             }
         }
@@ -177,18 +241,43 @@ impl NoteTemplateContext {
         // the code simple
         // May try leptos next and circle back, managing web requests
         // in an MPA is a bit more tricky than expected.
-        let note = fetch_note(&api_addr, note_id, false).await?;
-
+        let (note, title, from_snapshot) = match upstream::call("fetch_note", fetch_note(&api_addr, note_id, false)).await {
+            Ok(note) => {
+                state.snapshot.set_note(note_id, &note.title, &note);
+                (Value::from_serialize(&note), note.title, false)
+            }
+            Err(e) if is_not_found(&e) => return Err(e.into()),
+            // Serve a recently viewed copy while the API is unreachable
+            Err(e) => match state.snapshot.note(note_id) {
+                Some(saved) => {
+                    warn!(error = ?e, note_id, "Failed to get note, using the snapshot");
+                    (saved.note, saved.title, true)
+                }
+                None => return Err(e.into()),
+            },
+        };
+        let read_only = body_handler.degraded || from_snapshot;
 
-        let ctx = context! { ..body_handler.ctx, ..context! {
-            note => note,
-            breadcrumbs => breadcrumbs,
-            forwardlinks => forward_links,
-            backlinks => backlinks,
-            tags => tags,
-        }};
+        let ctx = note_page_context(
+            body_handler.ctx,
+            &state.snapshot,
+            read_only,
+            context! {
+                note => note,
+                breadcrumbs => breadcrumbs,
+                forwardlinks => forward_links,
+                backlinks => backlinks,
+                tags => tags,
+            },
+        );
 
-        Ok(Self { api_addr, ctx })
+        Ok(Self {
+            api_addr,
+            snapshot: state.snapshot.clone(),
+            title,
+            read_only,
+            ctx,
+        })
     }
 
     // TODO use this to set note_content in the template
@@ -196,12 +285,12 @@ impl NoteTemplateContext {
     // that way the code stays simple but it's not fetched for reading
     #[allow(dead_code)]
     pub async fn get_note_with_content(&self, id: i32) -> Result<NoteWithoutFts, NoteError> {
-        fetch_note(&self.api_addr, id, false).await
+        upstream::call("fetch_note", fetch_note(&self.api_addr, id, false)).await
     }
 
     async fn get_tag_notes(&self, tag_id: i32) -> Vec<NoteWithoutFts> {
         // Get note-tag relations
-        match list_note_tags(&self.api_addr).await {
+        match upstream::call("list_note_tags", list_note_tags(&self.api_addr)).await {
             Ok(note_tags) => {
                 // Filter for relevant tags
                 let relevant_note_tags: Vec<_> = note_tags
@@ -212,14 +301,14 @@ impl NoteTemplateContext {
                 // Get note details for each relevant tag
                 let mut notes = Vec::new();
                 for note_tag in relevant_note_tags {
-                    if let Ok(note) = fetch_note(&self.api_addr, note_tag.note_id, false).await {
+                    if let Ok(note) = upstream::call("fetch_note", fetch_note(&self.api_addr, note_tag.note_id, false)).await {
                         notes.push(note);
                     }
                 }
                 notes
             }
             Err(e) => {
-                eprintln!("Failed to get note-tag relations: {:#?}", e);
+                error!(error = ?e, "Failed to get note-tag relations");
                 Vec::new()
             }
         }
@@ -229,6 +318,61 @@ impl NoteTemplateContext {
         &self,
         id: i32,
     ) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
-        Ok(get_note_rendered_html(&self.api_addr, id).await?)
+        match upstream::call("get_note_rendered_html", get_note_rendered_html(&self.api_addr, id)).await {
+            Ok(html) => {
+                self.snapshot.set_rendered_html(id, &html);
+                Ok(html)
+            }
+            Err(e) if is_not_found(&e) => Err(e.into()),
+            Err(e) => match self.snapshot.note(id).and_then(|n| n.rendered_html) {
+                Some(html) => {
+                    warn!(error = ?e, note_id = id, "Failed to render note, using the snapshot");
+                    Ok(html)
+                }
+                None => Err(e.into()),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::templates::ENV;
+
+    #[test]
+    fn snapshot_notes_are_read_only_even_when_the_tree_loaded() {
+        let snapshot = Snapshot::new();
+        snapshot.set_note(7, "Saved note", &serde_json::json!({ "id": 7, "title": "Saved note" }));
+
+        // What `BodyTemplateContext` builds when the note tree fetch succeeded
+        let layout = context! {
+            tree => Vec::<Value>::new(),
+            ..snapshot_status(&snapshot, false)
+        };
+        let ctx = note_page_context(layout, &snapshot, true, context! { note => snapshot.note(7) });
+
+        assert_eq!(ctx.get_attr("api_unavailable").unwrap(), Value::from(true));
+        let banner = ENV
+            .get_template("degraded_banner.html")
+            .and_then(|t| t.render(&ctx))
+            .expect("banner renders");
+        assert!(banner.contains("The Draftsmith backend is unreachable"));
+        assert!(banner.contains("read-only snapshot"));
+        assert!(banner.contains("Saved note"));
+    }
+
+    #[test]
+    fn live_notes_keep_the_layout_status() {
+        let snapshot = Snapshot::new();
+        let layout = snapshot_status(&snapshot, false);
+        let ctx = note_page_context(layout, &snapshot, false, context! {});
+
+        assert_eq!(ctx.get_attr("api_unavailable").unwrap(), Value::from(false));
+        let banner = ENV
+            .get_template("degraded_banner.html")
+            .and_then(|t| t.render(&ctx))
+            .expect("banner renders");
+        assert!(banner.trim().is_empty());
     }
 }
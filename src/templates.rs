@@ -1,9 +1,12 @@
-use crate::flash::{FlashMessage, FlashMessageStore};
-use axum::response::Redirect;
+use crate::datetime;
+use crate::error::AppError;
+use crate::metrics;
+use crate::urls::url_for;
+use axum::response::Html;
 use include_dir::{include_dir, Dir};
-use minijinja::{Environment, Error, Value};
+use minijinja::{Environment, Error, Template, Value};
 use once_cell::sync::Lazy;
-use tower_sessions::Session;
+use std::time::Instant;
 
 static TEMPLATE_DIR: Dir<'_> = include_dir!("$CARGO_MANIFEST_DIR/templates");
 
@@ -22,67 +25,29 @@ pub static ENV: Lazy<Environment<'static>> = Lazy::new(|| {
         }
     }
 
-    /*
-    1 Take, e.g. "2024-11-22T04:54:33.933017"
-    2 Split at "T" to get ["2024-11-22", "04:54:33.933017"]
-    3 Keep the date part and first 5 chars of time
-    4 Join with space to get "2024-11-22 04:54"
-
-    Datetime libraries are not used because they were a pain.
-     */
-    fn format_datetime(value: Value) -> Result<String, Error> {
-        if let Some(datetime_str) = value.as_str() {
-            // Split at 'T' and process
-            if let Some((date, time)) = datetime_str.split_once('T') {
-                // Take first 5 chars of time (HH:MM)
-                let time = time.get(0..5).unwrap_or("00:00");
-                return Ok(format!("{} {}", date, time));
-            }
-        }
-
-        // If anything fails, return the original value
-        Ok(value.to_string())
-    }
-
-
     // Add the contains filter
     env.add_filter("contains", |list: Vec<i32>, item: i32| {
         list.contains(&item)
     });
-    env.add_filter("datetime", format_datetime);
+    // Timestamps as <time> elements in the reader's timezone
+    env.add_filter("datetime", datetime::absolute_filter);
+    env.add_filter("timeago", datetime::relative_filter);
+    // Prefix app paths with the base path, e.g. {{ url_for('/note/' ~ id) }}
+    env.add_function("url_for", |path: &str| url_for(path));
 
     env
 });
 
-pub fn render_template(template_name: &str, context: minijinja::value::Value) -> String {
-    match ENV.get_template(template_name) {
-        Ok(tmpl) => match tmpl.render(context) {
-            Ok(html) => html,
-            Err(err) => handle_template_error(err),
-        },
-        Err(err) => handle_template_error(err),
-    }
+/// Render a template, recording how long it took
+pub fn render_timed(template: &Template, ctx: Value) -> Result<String, Error> {
+    let started = Instant::now();
+    let result = template.render(ctx);
+    metrics::record_template_render(template.name(), started.elapsed());
+    result
 }
 
-pub async fn handle_not_found(session: Session) -> Redirect {
-    session
-        .set_flash(FlashMessage::error("Page not found"))
-        .await
-        .unwrap_or_else(|e| {
-            eprintln!("Failed to set flash message: {:#?}", e);
-        });
-
-    Redirect::to("/recent")
-}
-
-pub fn handle_template_error(err: Error) -> String {
-    eprintln!("Could not render template: {:#}", err);
-    // render causes as well
-    let mut err = &err as &dyn std::error::Error;
-    while let Some(next_err) = err.source() {
-        eprintln!();
-        eprintln!("caused by: {:#}", next_err);
-        err = next_err;
-    }
-    String::from("<h1>Error rendering Template</h1></br> See STDERR for more information")
+/// Load and render a page, a broken template becomes a 500 page
+pub fn render_page(template_name: &str, ctx: Value) -> Result<Html<String>, AppError> {
+    let template = ENV.get_template(template_name)?;
+    Ok(Html(render_timed(&template, ctx)?))
 }
@@ -0,0 +1,76 @@
+use axum::{
+    extract::Host,
+    http::{StatusCode, Uri},
+    response::{IntoResponse, Redirect, Response},
+    Router,
+};
+use axum_server::tls_rustls::RustlsConfig;
+use std::path::PathBuf;
+use tracing::{error, warn, info};
+
+/// Load the certificate and key, panicking like the other startup failures
+pub async fn load_rustls_config(cert: &PathBuf, key: &PathBuf) -> RustlsConfig {
+    RustlsConfig::from_pem_file(cert, key)
+        .await
+        .unwrap_or_else(|e| {
+            panic!(
+                "Unable to load TLS certificate {} and key {}. Error: {:#}",
+                cert.display(),
+                key.display(),
+                e
+            )
+        })
+}
+
+/// Reload the certificate and key whenever the process receives SIGHUP.
+///
+/// A failed reload keeps serving the previous certificate.
+#[cfg(unix)]
+pub async fn reload_on_sighup(rustls_config: RustlsConfig, cert: PathBuf, key: PathBuf) {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut hangups = match signal(SignalKind::hangup()) {
+        Ok(stream) => stream,
+        Err(e) => {
+            warn!(error = %e, "Unable to listen for SIGHUP, TLS reload disabled");
+            return;
+        }
+    };
+
+    while hangups.recv().await.is_some() {
+        match rustls_config.reload_from_pem_file(&cert, &key).await {
+            Ok(()) => info!(cert = %cert.display(), "Reloaded TLS certificate"),
+            Err(e) => error!(error = %e, "Failed to reload TLS certificate"),
+        }
+    }
+}
+
+#[cfg(not(unix))]
+pub async fn reload_on_sighup(_rustls_config: RustlsConfig, _cert: PathBuf, _key: PathBuf) {}
+
+/// Send every plain HTTP request to the same host and path over HTTPS
+pub fn https_redirect_app(https_port: u16) -> Router {
+    Router::new().fallback(move |Host(host): Host, uri: Uri| async move {
+        redirect_to_https(&host, &uri, https_port)
+    })
+}
+
+fn redirect_to_https(host: &str, uri: &Uri, https_port: u16) -> Response {
+    // Drop the HTTP port, keeping IPv6 literals like "[::1]" intact
+    let hostname = match host.rsplit_once(':') {
+        Some((name, port)) if !port.contains(']') => name,
+        _ => host,
+    };
+    if hostname.is_empty() {
+        return (StatusCode::BAD_REQUEST, "Missing Host header").into_response();
+    }
+
+    let authority = if https_port == 443 {
+        hostname.to_string()
+    } else {
+        format!("{}:{}", hostname, https_port)
+    };
+    let path = uri.path_and_query().map(|p| p.as_str()).unwrap_or("/");
+
+    Redirect::permanent(&format!("https://{}{}", authority, path)).into_response()
+}
@@ -0,0 +1,28 @@
+//! Wrapper for calls to the Draftsmith API client, so each one
+//! gets its own span and timing no matter which route makes it
+
+use crate::metrics;
+use std::fmt::Debug;
+use std::future::Future;
+use std::time::Instant;
+use tracing::{debug, info_span, Instrument};
+
+/// Run a client call inside an `upstream` span named after the client function
+pub async fn call<T, E: Debug>(
+    name: &'static str,
+    request: impl Future<Output = Result<T, E>>,
+) -> Result<T, E> {
+    let span = info_span!("upstream", call = name);
+    let started = Instant::now();
+    let result = request.instrument(span.clone()).await;
+    let elapsed = started.elapsed();
+    let latency_ms = elapsed.as_millis() as u64;
+    metrics::record_upstream_call(name, elapsed, result.is_err());
+
+    span.in_scope(|| match &result {
+        Ok(_) => debug!(latency_ms, "upstream call finished"),
+        Err(e) => debug!(latency_ms, error = ?e, "upstream call failed"),
+    });
+
+    result
+}
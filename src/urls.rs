@@ -0,0 +1,100 @@
+use axum::response::Redirect;
+use std::sync::OnceLock;
+
+/// Set once at startup from `server.base_path`, e.g. "/notes" or ""
+static BASE_PATH: OnceLock<String> = OnceLock::new();
+
+/// Record the path prefix the app is mounted under.
+/// Later calls are ignored, the prefix can't change while serving.
+pub fn set_base_path(base_path: &str) {
+    let _ = BASE_PATH.set(base_path.to_string());
+}
+
+pub fn base_path() -> &'static str {
+    BASE_PATH.get().map(String::as_str).unwrap_or("")
+}
+
+/// Prefix an app path like "/note/3" with the base path.
+///
+/// Also exposed to templates as `url_for`.
+pub fn url_for(path: &str) -> String {
+    let base = base_path();
+    if path.is_empty() || path == "/" {
+        // The root of a sub-path is "/notes/", not "/notes"
+        return format!("{}/", base);
+    }
+    if path.starts_with('/') {
+        format!("{}{}", base, path)
+    } else {
+        format!("{}/{}", base, path)
+    }
+}
+
+/// Redirect to an app path, respecting the base path
+pub fn redirect(path: &str) -> Redirect {
+    Redirect::to(&url_for(path))
+}
+
+/// Redirect to a path the submitting page filled in with its own location,
+/// or to the app path `fallback` if it isn't a plain local path.
+///
+/// Browsers treat `\` like `/`, so `/\evil.com` would leave the site just
+/// like `//evil.com` does.
+pub fn safe_redirect(redirect_to: Option<&str>, fallback: &str) -> Redirect {
+    match redirect_to {
+        Some(path) if is_local_path(path) => Redirect::to(path),
+        _ => redirect(fallback),
+    }
+}
+
+fn is_local_path(path: &str) -> bool {
+    path.starts_with('/')
+        && !path.starts_with("//")
+        && !path.contains('\\')
+        && !path.chars().any(char::is_control)
+}
+
+/// Prefix root-relative links in note HTML rendered by the API,
+/// e.g. `![](/m/photo.png)` or `[see](/note/3)`, with the base path
+pub fn prefix_rendered_links(html: &str) -> String {
+    let base = base_path();
+    if base.is_empty() {
+        return html.to_string();
+    }
+    let mut out = html.to_string();
+    for attr in ["src", "href"] {
+        let needle = format!("{}=\"/", attr);
+        let mut rewritten = String::with_capacity(out.len());
+        let mut rest = out.as_str();
+        while let Some(pos) = rest.find(&needle) {
+            let after = &rest[pos + needle.len()..];
+            rewritten.push_str(&rest[..pos + needle.len() - 1]);
+            // Leave protocol-relative URLs alone
+            if !after.starts_with('/') {
+                rewritten.push_str(base);
+            }
+            rewritten.push('/');
+            rest = after;
+        }
+        rewritten.push_str(rest);
+        out = rewritten;
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_local_paths_are_followed() {
+        assert!(is_local_path("/recent?p=2"));
+        assert!(is_local_path("/note/3"));
+        assert!(!is_local_path("https://evil.com"));
+        assert!(!is_local_path("//evil.com"));
+        assert!(!is_local_path("/\\evil.com"));
+        assert!(!is_local_path("/note\\..\\x"));
+        assert!(!is_local_path("/\tevil.com"));
+        assert!(!is_local_path(""));
+    }
+}
@@ -0,0 +1,57 @@
+import { Controller } from "../stimulus/stimulus.js"
+
+// Drag to reorder the pinned notes, the new order is saved right away.
+// A custom type keeps the tree controller from treating the drop as a move.
+const PIN_TYPE = 'application/x-draftsmith-pin'
+
+export default class extends Controller {
+  dragStart(event) {
+    this.dragged = event.target.closest('.pinned-item')
+    event.dataTransfer.setData(PIN_TYPE, this.dragged.dataset.pinId)
+    event.dataTransfer.effectAllowed = 'move'
+    this.dragged.classList.add('dragging')
+  }
+
+  dragOver(event) {
+    if (!this.dragged) return
+    event.preventDefault()
+    event.stopPropagation()
+
+    const target = event.target.closest('.pinned-item')
+    if (!target || target === this.dragged) return
+
+    // Insert before or after depending on which half is hovered
+    const rect = target.getBoundingClientRect()
+    const after = event.clientY > rect.top + rect.height / 2
+    target.parentElement.insertBefore(this.dragged, after ? target.nextSibling : target)
+  }
+
+  async drop(event) {
+    if (!this.dragged) return
+    event.preventDefault()
+    event.stopPropagation()
+
+    const ids = Array.from(this.element.querySelectorAll('.pinned-item'))
+      .map(item => Number(item.dataset.pinId))
+
+    try {
+      const response = await fetch(window.urlFor('/pins/order'), {
+        method: 'POST',
+        headers: { 'Content-Type': 'application/json' },
+        body: JSON.stringify({ ids }),
+      })
+      if (!response.ok) {
+        throw new Error(`Saving pin order failed: ${response.statusText}`)
+      }
+    } catch (error) {
+      console.error('Error saving pin order:', error)
+      // Show the order the server kept
+      window.location.reload()
+    }
+  }
+
+  dragEnd() {
+    this.dragged?.classList.remove('dragging')
+    this.dragged = null
+  }
+}
@@ -0,0 +1,131 @@
+import { Controller } from "../stimulus/stimulus.js"
+
+export default class extends Controller {
+  static targets = ["input", "results", "tagId"]
+
+  connect() {
+    this.suggestions = []
+    this.selected = -1
+    this.timeout = null
+
+    // Hide the suggestions when clicking elsewhere
+    this.hideOnOutsideClick = (event) => {
+      if (!this.element.contains(event.target)) {
+        this.hide()
+      }
+    }
+    document.addEventListener('click', this.hideOnOutsideClick)
+  }
+
+  disconnect() {
+    document.removeEventListener('click', this.hideOnOutsideClick)
+  }
+
+  search() {
+    // Typing invalidates any previously picked tag
+    this.tagIdTarget.value = ''
+
+    clearTimeout(this.timeout)
+    this.timeout = setTimeout(() => this.fetchSuggestions(), 150)
+  }
+
+  async fetchSuggestions() {
+    const query = this.inputTarget.value.trim()
+    if (!query) {
+      this.hide()
+      return
+    }
+
+    try {
+      const response = await fetch(window.urlFor(`/api/tags/suggest?q=${encodeURIComponent(query)}`))
+      if (!response.ok) {
+        throw new Error(`Suggest failed: ${response.statusText}`)
+      }
+      this.suggestions = await response.json()
+    } catch (error) {
+      console.error('Error fetching tag suggestions:', error)
+      this.suggestions = []
+    }
+
+    this.selected = -1
+    this.render(query)
+  }
+
+  render(query) {
+    const exists = this.suggestions.some(tag => tag.title.toLowerCase() === query.toLowerCase())
+
+    const items = this.suggestions.map((tag, index) => `
+      <li>
+        <a data-index="${index}" class="${index === this.selected ? 'active' : ''} flex justify-between">
+          <span>${this.escape(tag.title)}</span>
+          <span class="text-xs opacity-60">${this.escape(tag.path)}</span>
+        </a>
+      </li>
+    `)
+
+    if (!exists) {
+      items.push(`
+        <li>
+          <a data-index="-1" class="${this.selected === this.suggestions.length ? 'active' : ''}">
+            Create "${this.escape(query)}"
+          </a>
+        </li>
+      `)
+    }
+
+    this.resultsTarget.innerHTML = items.join('')
+    this.resultsTarget.classList.remove('hidden')
+
+    this.resultsTarget.querySelectorAll('a').forEach(link => {
+      link.addEventListener('click', () => this.pick(parseInt(link.dataset.index)))
+    })
+  }
+
+  navigate(event) {
+    if (this.resultsTarget.classList.contains('hidden')) return
+
+    // The "Create" entry sits after the suggestions
+    const count = this.resultsTarget.querySelectorAll('a').length
+    if (event.key === 'ArrowDown') {
+      event.preventDefault()
+      this.selected = Math.min(this.selected + 1, count - 1)
+      this.render(this.inputTarget.value.trim())
+    } else if (event.key === 'ArrowUp') {
+      event.preventDefault()
+      this.selected = Math.max(this.selected - 1, 0)
+      this.render(this.inputTarget.value.trim())
+    } else if (event.key === 'Enter' && this.selected >= 0) {
+      event.preventDefault()
+      this.pick(this.selected < this.suggestions.length ? this.selected : -1)
+    } else if (event.key === 'Escape') {
+      this.hide()
+    }
+  }
+
+  // index -1 creates the typed tag
+  pick(index) {
+    const tag = this.suggestions[index]
+    if (tag) {
+      this.inputTarget.value = tag.title
+      this.tagIdTarget.value = tag.id
+    } else {
+      this.tagIdTarget.value = ''
+    }
+    this.hide()
+    this.element.requestSubmit()
+  }
+
+  submit() {
+    this.hide()
+  }
+
+  hide() {
+    this.resultsTarget.classList.add('hidden')
+  }
+
+  escape(text) {
+    const div = document.createElement('div')
+    div.textContent = text
+    return div.innerHTML
+  }
+}
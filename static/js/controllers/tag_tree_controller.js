@@ -1,4 +1,4 @@
-import { Controller } from "/static/js/stimulus/stimulus.js"
+import { Controller } from "../stimulus/stimulus.js"
 
 export default class extends Controller {
     connect() {
@@ -77,7 +77,7 @@ export default class extends Controller {
         }
 
         try {
-            const response = await fetch(`/tag/${draggedTagId}/set_parent`, {
+            const response = await fetch(window.urlFor(`/tag/${draggedTagId}/set_parent`), {
                 method: 'POST',
                 headers: {
                     'Content-Type': 'application/x-www-form-urlencoded',
@@ -93,12 +93,12 @@ export default class extends Controller {
                 throw new Error(`Move failed: ${errorText}`)
             }
 
-            window.location.href = '/manage_tags'
+            window.location.href = window.urlFor('/manage_tags')
 
         } catch (error) {
             console.error('Error moving tag:', error)
             alert('Failed to update tag hierarchy: ' + error.message)
-            window.location.href = '/manage_tags'
+            window.location.href = window.urlFor('/manage_tags')
         }
     }
 
@@ -119,7 +119,7 @@ export default class extends Controller {
         if (!draggedTagId) return
 
         try {
-            const response = await fetch(`/tag/${draggedTagId}/unset_parent`, {
+            const response = await fetch(window.urlFor(`/tag/${draggedTagId}/unset_parent`), {
                 method: 'POST',
                 headers: {
                     'Content-Type': 'application/x-www-form-urlencoded',
@@ -132,12 +132,12 @@ export default class extends Controller {
                 throw new Error(`Detach failed: ${errorText}`)
             }
 
-            window.location.href = '/manage_tags'
+            window.location.href = window.urlFor('/manage_tags')
 
         } catch (error) {
             console.error('Error detaching tag:', error)
             alert('Failed to detach tag: ' + error.message)
-            window.location.href = '/manage_tags'
+            window.location.href = window.urlFor('/manage_tags')
         }
     }
 }
@@ -1,4 +1,4 @@
-import { Controller } from "/static/js/stimulus/stimulus.js"
+import { Controller } from "../stimulus/stimulus.js"
 
 export default class extends Controller {
   connect() {
@@ -8,8 +8,22 @@ export default class extends Controller {
     this.originalDetailsStates = new WeakMap()
 
     // Initialize all note items as draggable
-    this.element.querySelectorAll('.note-item').forEach(item => {
-      console.log("Setting up draggable item:", item);
+    this.setupItems(this.element)
+
+    // Collapsed subtrees are loaded the first time they are opened,
+    // toggle doesn't bubble so listen during capture
+    this.element.addEventListener('toggle', this.handleToggle.bind(this), true)
+
+    // Remember what the user expands, hovering and dragging only peek
+    this.element.addEventListener('click', this.handleSummaryClick.bind(this))
+
+    // Add drop zone for detaching notes
+    document.body.addEventListener('dragover', this.handleBodyDragOver.bind(this))
+    document.body.addEventListener('drop', this.handleBodyDrop.bind(this))
+  }
+
+  setupItems(root) {
+    root.querySelectorAll('.note-item').forEach(item => {
       item.addEventListener('dragstart', this.handleDragStart.bind(this))
       item.addEventListener('dragover', this.handleDragOver.bind(this))
       item.addEventListener('drop', this.handleDrop.bind(this))
@@ -18,10 +32,57 @@ export default class extends Controller {
       item.addEventListener('mouseenter', this.handleNoteHover.bind(this))
       item.addEventListener('mouseleave', this.handleNoteLeave.bind(this))
     })
+  }
 
-    // Add drop zone for detaching notes
-    document.body.addEventListener('dragover', this.handleBodyDragOver.bind(this))
-    document.body.addEventListener('drop', this.handleBodyDrop.bind(this))
+  async handleToggle(event) {
+    const details = event.target
+    if (!(details instanceof HTMLDetailsElement) || !details.open) return
+
+    const url = details.dataset.childrenUrl
+    if (!url || details.dataset.childrenLoaded) return
+    // Mark before fetching so hovering back and forth doesn't fetch twice
+    details.dataset.childrenLoaded = 'loading'
+
+    const list = details.querySelector(':scope > ul')
+    try {
+      const response = await fetch(url)
+      if (!response.ok) {
+        throw new Error(`Loading children failed: ${response.statusText}`)
+      }
+      list.innerHTML = await response.text()
+      this.setupItems(list)
+      details.dataset.childrenLoaded = 'true'
+    } catch (error) {
+      console.error('Error loading children:', error)
+      // Allow another attempt on the next toggle
+      delete details.dataset.childrenLoaded
+    }
+  }
+
+  handleSummaryClick(event) {
+    const summary = event.target.closest('summary')
+    // Clicking the title follows the link instead
+    if (!summary || event.target.closest('a')) return
+
+    const details = summary.parentElement
+    const noteItem = details.closest('.note-item')
+    if (!noteItem) return
+
+    let open
+    if (this.originalDetailsStates.has(details)) {
+      // Hovering already opened it, a click keeps it open
+      event.preventDefault()
+      this.originalDetailsStates.delete(details)
+      open = true
+    } else {
+      open = !details.open
+    }
+
+    fetch(window.urlFor('/prefs/tree_expanded'), {
+      method: 'POST',
+      headers: { 'Content-Type': 'application/json' },
+      body: JSON.stringify({ id: Number(noteItem.dataset.noteId), open }),
+    }).catch(error => console.error('Error saving tree state:', error))
   }
 
   handleDragStart(event) {
@@ -148,7 +209,7 @@ export default class extends Controller {
 
     try {
         // Make the API call to move the note
-        const response = await fetch(`/note/${draggedNoteId}/move`, {
+        const response = await fetch(window.urlFor(`/note/${draggedNoteId}/move`), {
             method: 'POST',
             headers: {
                 'Content-Type': 'application/x-www-form-urlencoded',
@@ -164,12 +225,12 @@ export default class extends Controller {
         }
 
         // Redirect to the note's page to show the result and flash message
-        window.location.href = `/note/${draggedNoteId}`;
+        window.location.href = window.urlFor(`/note/${draggedNoteId}`);
 
     } catch (error) {
         console.error('Error moving note:', error);
         // Force a page reload to show any error flash messages
-        window.location.href = `/note/${draggedNoteId}`;
+        window.location.href = window.urlFor(`/note/${draggedNoteId}`);
     }
   }
 
@@ -182,6 +243,9 @@ export default class extends Controller {
   }
 
   async handleBodyDrop(event) {
+    // A tree hidden behind the filter results leaves the drop to the visible one
+    if (!this.element.offsetParent) return
+
     // Only handle drops outside the tree
     if (!event.target.closest('.note-tree')) {
         event.preventDefault()
@@ -192,7 +256,7 @@ export default class extends Controller {
 
         try {
             // Make the API call to detach the note
-            const response = await fetch(`/note/${draggedNoteId}/move`, {
+            const response = await fetch(window.urlFor(`/note/${draggedNoteId}/move`), {
                 method: 'POST',
                 headers: {
                     'Content-Type': 'application/x-www-form-urlencoded',
@@ -207,11 +271,11 @@ export default class extends Controller {
             }
 
             // Redirect to the note's page to show the result
-            window.location.href = `/note/${draggedNoteId}`
+            window.location.href = window.urlFor(`/note/${draggedNoteId}`)
 
         } catch (error) {
             console.error('Error detaching note:', error)
-            window.location.href = `/note/${draggedNoteId}`
+            window.location.href = window.urlFor(`/note/${draggedNoteId}`)
         }
     }
   }
@@ -0,0 +1,65 @@
+import { Controller } from "../stimulus/stimulus.js"
+
+// Filters the sidebar tree on the server, showing the pruned tree
+// in place of the paged one until the filter is cleared
+export default class extends Controller {
+  static targets = ["input", "tree", "results"]
+  static values = { current: Number }
+
+  connect() {
+    this.timeout = null
+    this.request = null
+  }
+
+  filter() {
+    clearTimeout(this.timeout)
+    this.timeout = setTimeout(() => this.fetchResults(), 200)
+  }
+
+  clear(event) {
+    if (event.key === 'Escape') {
+      this.inputTarget.value = ''
+      this.fetchResults()
+    }
+  }
+
+  async fetchResults() {
+    const query = this.inputTarget.value.trim()
+    if (!query) {
+      this.showTree()
+      return
+    }
+
+    // Drop answers to queries the user has already typed past
+    this.request?.abort()
+    this.request = new AbortController()
+
+    const params = new URLSearchParams({ q: query })
+    if (this.hasCurrentValue) {
+      params.set('current', this.currentValue)
+    }
+
+    try {
+      const response = await fetch(window.urlFor(`/tree/filter?${params}`), {
+        signal: this.request.signal,
+      })
+      if (!response.ok) {
+        throw new Error(`Filter failed: ${response.statusText}`)
+      }
+      this.resultsTarget.innerHTML = await response.text()
+      this.treeTarget.classList.add('hidden')
+      this.resultsTarget.classList.remove('hidden')
+    } catch (error) {
+      if (error.name !== 'AbortError') {
+        console.error('Error filtering tree:', error)
+      }
+    }
+  }
+
+  showTree() {
+    this.request?.abort()
+    this.resultsTarget.innerHTML = ''
+    this.resultsTarget.classList.add('hidden')
+    this.treeTarget.classList.remove('hidden')
+  }
+}
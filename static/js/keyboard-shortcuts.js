@@ -8,13 +8,23 @@ class KeyboardShortcuts {
                 action: () => this.showShortcutsHelp()
             },
             // Navigation shortcuts
-            prevPage: {
+            historyBack: {
                 key: 'ArrowLeft',
                 modifier: 'Alt',
+                action: () => this.navigateHistory('back')
+            },
+            historyForward: {
+                key: 'ArrowRight',
+                modifier: 'Alt',
+                action: () => this.navigateHistory('forward')
+            },
+            prevPage: {
+                key: 'PageUp',
+                modifier: 'Alt',
                 action: () => this.navigateToPage('prev')
             },
             nextPage: {
-                key: 'ArrowRight',
+                key: 'PageDown',
                 modifier: 'Alt',
                 action: () => this.navigateToPage('next')
             },
@@ -49,8 +59,16 @@ class KeyboardShortcuts {
                 key: 'Enter',
                 modifier: 'Control',
                 action: () => this.submitForm()
+            },
+            quickSwitcher: {
+                key: 'k',
+                modifier: 'Control',
+                action: () => this.showQuickSwitcher()
             }
         };
+
+        // Cached copy of /api/index.json, revalidated through its ETag
+        this.noteIndex = null;
         
         this.init();
     }
@@ -115,6 +133,13 @@ class KeyboardShortcuts {
         activeElement.focus();
     }
 
+    // Step through the notes viewed in this session, see /history
+    navigateHistory(direction) {
+        const noteId = this.currentNoteId();
+        const query = noteId ? `?from=${noteId}` : '';
+        window.location.href = window.urlFor(`/history/${direction}${query}`);
+    }
+
     navigateToPage(direction) {
         const urlParams = new URLSearchParams(window.location.search);
         const currentPage = parseInt(urlParams.get('page')) || 1;
@@ -159,6 +184,203 @@ class KeyboardShortcuts {
         }
     }
 
+    currentNoteId() {
+        const match = window.location.pathname.match(/\/(?:note|edit|assign_tags)\/(\d+)/);
+        return match ? match[1] : null;
+    }
+
+    quickSwitcherActions() {
+        const actions = [
+            { title: 'New note', path: 'Action', href: window.urlFor('/create') },
+            { title: 'Recent notes', path: 'Action', href: window.urlFor('/recent') },
+            { title: 'History', path: 'Action', href: window.urlFor('/history') },
+            { title: 'Manage tags', path: 'Action', href: window.urlFor('/manage_tags') },
+            { title: 'Upload asset', path: 'Action', href: window.urlFor('/upload_asset') },
+        ];
+
+        const noteId = this.currentNoteId();
+        if (noteId) {
+            actions.push(
+                { title: 'New child note', path: 'Action', href: window.urlFor(`/create/${noteId}`) },
+                { title: 'New sibling note', path: 'Action', href: window.urlFor(`/create/${noteId}?as_sibling=true`) },
+                { title: 'Edit note', path: 'Action', href: window.urlFor(`/edit/${noteId}`) },
+                { title: 'Move note', path: 'Action', href: window.urlFor(`/note/${noteId}/move`) },
+                { title: 'Assign tags', path: 'Action', href: window.urlFor(`/assign_tags/${noteId}`) },
+            );
+        }
+
+        return actions.map(action => ({ ...action, kind: 'action' }));
+    }
+
+    async loadNoteIndex() {
+        try {
+            // no-cache makes the browser revalidate with If-None-Match
+            const response = await fetch(window.urlFor('/api/index.json'), { cache: 'no-cache' });
+            if (!response.ok) {
+                throw new Error(`Index request failed: ${response.statusText}`);
+            }
+            const index = await response.json();
+            this.noteIndex = [
+                ...index.notes.map(note => ({
+                    kind: 'note',
+                    title: note.title,
+                    path: note.path,
+                    href: window.urlFor(`/note/${note.id}`)
+                })),
+                ...index.tags.map(tag => ({
+                    kind: 'tag',
+                    title: `#${tag.title}`,
+                    path: tag.path,
+                    href: window.urlFor(`/tags/${tag.id}`)
+                })),
+            ];
+        } catch (error) {
+            console.error('Error loading note index:', error);
+            this.noteIndex = this.noteIndex || [];
+        }
+        return this.noteIndex;
+    }
+
+    // Subsequence match, rewarding consecutive characters and word starts.
+    // Returns null when the query doesn't match at all.
+    fuzzyScore(query, text) {
+        const haystack = text.toLowerCase();
+        let score = 0;
+        let lastIndex = -1;
+
+        for (const char of query.toLowerCase()) {
+            const index = haystack.indexOf(char, lastIndex + 1);
+            if (index === -1) {
+                return null;
+            }
+            if (index === lastIndex + 1) {
+                score += 5;
+            }
+            if (index === 0 || ' /-_#'.includes(haystack[index - 1])) {
+                score += 3;
+            }
+            score -= index - lastIndex - 1;
+            lastIndex = index;
+        }
+
+        return score;
+    }
+
+    filterQuickSwitcher(query, entries) {
+        if (!query) {
+            return entries.slice(0, 50);
+        }
+
+        return entries
+            .map(entry => {
+                const titleScore = this.fuzzyScore(query, entry.title);
+                const pathScore = this.fuzzyScore(query, entry.path);
+                // Prefer matches on the title over matches on the full path
+                const score = titleScore !== null
+                    ? titleScore + 10
+                    : pathScore;
+                return { entry, score };
+            })
+            .filter(result => result.score !== null)
+            .sort((a, b) => b.score - a.score)
+            .slice(0, 50)
+            .map(result => result.entry);
+    }
+
+    escapeHtml(text) {
+        const div = document.createElement('div');
+        div.textContent = text;
+        return div.innerHTML;
+    }
+
+    async showQuickSwitcher() {
+        const existingDialog = document.getElementById('quick-switcher');
+        if (existingDialog) {
+            existingDialog.remove();
+        }
+
+        const switcherContent = `
+            <dialog id="quick-switcher" class="modal modal-top sm:modal-middle">
+                <div class="modal-box">
+                    <input
+                        type="text"
+                        class="input input-bordered w-full mb-4"
+                        placeholder="Jump to a note, tag or action"
+                        autocomplete="off"
+                    />
+                    <ul class="menu bg-base-200 rounded-box w-full max-h-96 overflow-y-auto flex-nowrap"></ul>
+                </div>
+                <form method="dialog" class="modal-backdrop">
+                    <button>close</button>
+                </form>
+            </dialog>
+        `;
+
+        document.body.insertAdjacentHTML('beforeend', switcherContent);
+
+        const dialog = document.getElementById('quick-switcher');
+        const input = dialog.querySelector('input');
+        const list = dialog.querySelector('ul');
+        let results = [];
+        let selected = 0;
+
+        const actions = this.quickSwitcherActions();
+        let entries = [...actions, ...(this.noteIndex || [])];
+
+        const render = () => {
+            results = this.filterQuickSwitcher(input.value.trim(), entries);
+            selected = Math.min(selected, Math.max(results.length - 1, 0));
+            list.innerHTML = results
+                .map((entry, index) => `
+                    <li>
+                        <a href="${entry.href}" class="${index === selected ? 'active' : ''}">
+                            <span class="badge badge-sm badge-ghost">${entry.kind}</span>
+                            <span class="font-semibold">${this.escapeHtml(entry.title)}</span>
+                            <span class="text-xs opacity-60 truncate">${this.escapeHtml(entry.path)}</span>
+                        </a>
+                    </li>
+                `)
+                .join('') || '<li class="p-2 text-sm opacity-60">No matches</li>';
+            const active = list.querySelector('a.active');
+            if (active) {
+                active.scrollIntoView({ block: 'nearest' });
+            }
+        };
+
+        input.addEventListener('input', () => {
+            selected = 0;
+            render();
+        });
+
+        input.addEventListener('keydown', (event) => {
+            if (event.key === 'ArrowDown') {
+                event.preventDefault();
+                selected = Math.min(selected + 1, results.length - 1);
+                render();
+            } else if (event.key === 'ArrowUp') {
+                event.preventDefault();
+                selected = Math.max(selected - 1, 0);
+                render();
+            } else if (event.key === 'Enter') {
+                event.preventDefault();
+                if (results[selected]) {
+                    window.location.href = results[selected].href;
+                }
+            }
+        });
+
+        dialog.addEventListener('close', () => dialog.remove());
+
+        render();
+        dialog.showModal();
+        input.focus();
+
+        // Refresh the index in the background, re-rendering once it arrives
+        const index = await this.loadNoteIndex();
+        entries = [...actions, ...index];
+        render();
+    }
+
     showShortcutsHelp() {
         // Create help dialog HTML
         const helpContent = `